@@ -1,4 +1,5 @@
 use ray_tracer::{
+   cli::RenderArgs,
    primitives::{
         Canvas, Color,Point,  Tuple
     },
@@ -9,21 +10,22 @@ use ray_tracer::{
 };
 
 fn main(){
+    let args = RenderArgs::from_env(RenderArgs::new(500, 500, "samples/chapter_5"));
     let origin = Point::new(0.0,0.0,-5.0);
-    let mut canvas = Canvas::new(500, 500);
+    let mut canvas = Canvas::new(args.width, args.height);
     let wall_z = 9.0;
     let wall_size = 7.0;
-    let canvas_pixels = 500;
-    let pixel_size = wall_size/ canvas_pixels as f64;
-    let half = wall_size/ 2.0;
+    let pixel_size = wall_size/ args.width.min(args.height) as f64;
+    let half_width = pixel_size * args.width as f64 / 2.0;
+    let half_height = pixel_size * args.height as f64 / 2.0;
     let light_position = Point::new(-10.0, -10.0, -10.0);
     let light_color = Color::new(1.0, 1.0, 1.0);
     let light = PointLight::new(light_color, light_position);
     let sphere = Object::new_sphere().set_material(&Material::new().with_color(Color::new(1.0, 0.2, 1.0)));
-    for y in 0..canvas_pixels {
-        let world_y = half - pixel_size * y as f64;
-        for x in 0 .. canvas_pixels {
-            let world_x = -half + x as f64 * pixel_size;
+    for y in 0..args.height {
+        let world_y = half_height - pixel_size * y as f64;
+        for x in 0 .. args.width {
+            let world_x = -half_width + x as f64 * pixel_size;
             let position = Point::new(world_x, world_y,wall_z);
             let ray = Ray::new(origin, (position - origin).normalize());
             let xs = sphere.intersect(&ray);
@@ -32,10 +34,10 @@ fn main(){
                 let normal = hit.object().normal_at(&point);
                 let eye = -ray.direction();
                 let color = hit.object().material().lighting(&light, &point,&point, &eye, &normal, false);
-                canvas.write_pixel(y, x, color);
+                canvas.write_pixel(x, y, color);
             }
 
         }
     }
-    canvas.save_as_ppm("samples/chapter_5").unwrap();
+    canvas.save_as_ppm(&args.out).unwrap();
 }
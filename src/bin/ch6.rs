@@ -1,4 +1,5 @@
 use ray_tracer::{
+    cli::RenderArgs,
     primitives::{Color, Matrix, Point, Tuple, Vector},
     rtc::{
         camera::Camera, light::PointLight, material::Material, object::Object,
@@ -6,8 +7,9 @@ use ray_tracer::{
     },
 };
 fn main() {
+    let args = RenderArgs::from_env(RenderArgs::new(2000, 1000, "samples/chapter_7"));
     let floor = Object::new_sphere()
-        .set_transform(&Matrix::id().scale(10.0, 0.01, 10.0))
+        .set_transform(Matrix::id().scale(10.0, 0.01, 10.0))
         .set_material(
             &Material::new()
                 .with_color(Color::new(1.0, 0.9, 0.9))
@@ -15,15 +17,15 @@ fn main() {
         );
     let left_wall = Object::new_sphere()
         .set_transform(
-            &Matrix::id()
+            Matrix::id()
                 .scale(10.0, 0.01, 10.0)
                 .rotate_x(std::f64::consts::FRAC_PI_2)
                 .rotate_y(-std::f64::consts::FRAC_PI_4)
                 .translate(0.0, 0.0, 5.0),
         )
-        .set_material(&floor.material());
+        .set_material(floor.material());
     let right_wall = Object::new_sphere().set_transform(
-        &Matrix::id()
+        Matrix::id()
             .scale(10.0, 0.01, 10.0)
             .rotate_x(std::f64::consts::FRAC_PI_2)
             .rotate_y(std::f64::consts::FRAC_PI_4)
@@ -31,7 +33,7 @@ fn main() {
     );
 
     let middle = Object::new_sphere()
-        .set_transform(&Matrix::id().translate(-0.5, 1.0, 0.5))
+        .set_transform(Matrix::id().translate(-0.5, 1.0, 0.5))
         .set_material(
             &Material::new()
                 .with_color(Color::new(0.1, 1.0, 0.5))
@@ -40,7 +42,7 @@ fn main() {
         );
 
     let right = Object::new_sphere()
-        .set_transform(&Matrix::id().scale(0.5, 0.5, 0.5).translate(1.5, 0.5, -0.5))
+        .set_transform(Matrix::id().scale(0.5, 0.5, 0.5).translate(1.5, 0.5, -0.5))
         .set_material(
             &Material::new()
                 .with_color(Color::new(0.5, 1.0, 0.1))
@@ -50,7 +52,7 @@ fn main() {
 
     let left = Object::new_sphere()
         .set_transform(
-            &Matrix::id()
+            Matrix::id()
                 .scale(0.33, 0.33, 0.33)
                 .translate(-1.5, 0.33, -0.75),
         )
@@ -64,11 +66,11 @@ fn main() {
     let light_source = PointLight::new(Color::new(1.0, 1.0, 1.0), Point::new(-10.0, 10.0, -10.0));
     let world = World::new()
         .with_objects(vec![floor, left_wall, right_wall, middle, right, left])
-        .with_lights(vec![light_source]);
+        .with_lights(vec![Box::new(light_source)]);
 
     let camera = Camera::new(
-        2000,
-        1000,
+        args.width,
+        args.height,
         std::f64::consts::FRAC_PI_3,
         view_transform(
             Point::new(0.0, 1.5, -5.0),
@@ -78,5 +80,5 @@ fn main() {
     );
 
     let canvas = camera.render(&world);
-    canvas.save_as_ppm("samples/chapter_7").unwrap();
+    canvas.save_as_ppm(&args.out).unwrap();
 }
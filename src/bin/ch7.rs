@@ -1,4 +1,5 @@
 use ray_tracer::{
+    cli::RenderArgs,
     primitives::{Color, Matrix, Point, Tuple, Vector},
     rtc::{
         camera::Camera, light::PointLight, material::Material, object::Object, pattern::Pattern,
@@ -7,6 +8,7 @@ use ray_tracer::{
 };
 
 fn main() {
+    let args = RenderArgs::from_env(RenderArgs::new(2000, 1000, "samples/chapter_8"));
     let floor = Object::new_plane().set_material(
         &Material::new()
             .with_pattern(Pattern::new_checkers(
@@ -18,7 +20,7 @@ fn main() {
 
     let left_wall = Object::new_plane()
         .set_transform(
-            &Matrix::id()
+            Matrix::id()
                 .rotate_z(std::f64::consts::FRAC_PI_2)
                 .translate(-15.0, 0.0, 0.0)
         )
@@ -33,7 +35,7 @@ fn main() {
 
     let right_wall = Object::new_plane()
         .set_transform(
-            &Matrix::id()
+            Matrix::id()
                 .rotate_x(std::f64::consts::FRAC_PI_2)
                 .translate(0.0, 0.0, 15.0)
         )
@@ -46,7 +48,7 @@ fn main() {
                 .with_reflective(0.0),
         );
     let blue_sphere = Object::new_sphere()
-        .set_transform(&Matrix::id().translate(-8.0, 1.0, 5.0))
+        .set_transform(Matrix::id().translate(-8.0, 1.0, 5.0))
         .set_material(
             &Material::new()
                 .with_pattern(Pattern::new_gradient(
@@ -59,7 +61,7 @@ fn main() {
         );
 
     let refractive_sphere = Object::new_sphere()
-        .set_transform(&Matrix::id().translate(0.0, 1.5, 0.0))
+        .set_transform(Matrix::id().translate(0.0, 1.5, 0.0))
         .set_material(
             &Material::new()
                 .with_color(Color::new(0.1, 0.1, 0.1))
@@ -68,7 +70,7 @@ fn main() {
         );
 
     let red_sphere = Object::new_sphere()
-        .set_transform(&Matrix::id().scale(0.5, 0.5, 0.5).translate(1.5, 0.5, 5.0))
+        .set_transform(Matrix::id().scale(0.5, 0.5, 0.5).translate(1.5, 0.5, 5.0))
         .set_material(
             &Material::new()
                 .with_pattern(Pattern::new_gradient(
@@ -81,7 +83,7 @@ fn main() {
         );
     let green_sphere = Object::new_sphere()
         .set_transform(
-            &Matrix::id()
+            Matrix::id()
                 .scale(0.5, 0.5, 0.5)
                 .translate(-2.3, 0.5, -0.77),
         )
@@ -107,10 +109,10 @@ fn main() {
             left_wall,
             right_wall
         ])
-        .with_lights(vec![light_source]);
+        .with_lights(vec![Box::new(light_source)]);
     let camera = Camera::new(
-        2000,
-        1000,
+        args.width,
+        args.height,
         std::f64::consts::PI / 3.0,
         view_transform(
             Point::new(5.0, 1.5, -5.5),
@@ -119,5 +121,5 @@ fn main() {
         ),
     );
     let canvas = camera.render(&world);
-    canvas.save_as_ppm("samples/chapter_8").unwrap();
+    canvas.save_as_ppm(&args.out).unwrap();
 }
@@ -0,0 +1,83 @@
+use ray_tracer::{
+    cli::RenderArgs,
+    primitives::{Color, Matrix, Point, Tuple, Vector},
+    rtc::{
+        camera::Camera, light::PointLight, material::Material, object::Object, pattern::Pattern,
+        transformation::view_transform, world::World,
+    },
+};
+
+fn main() {
+    let args = RenderArgs::from_env(RenderArgs::new(1600, 900, "samples/reflections"));
+    let floor = Object::new_plane().set_material(
+        &Material::new()
+            .with_pattern(Pattern::new_checkers(
+                Color::new(0.9, 0.9, 0.9),
+                Color::new(0.2, 0.2, 0.2),
+            ))
+            .with_specular(0.0)
+            .with_reflective(0.4),
+    );
+
+    let back_wall = Object::new_plane()
+        .set_transform(
+            Matrix::id()
+                .rotate_x(std::f64::consts::FRAC_PI_2)
+                .translate(0.0, 0.0, 12.0),
+        )
+        .set_material(
+            &Material::new()
+                .with_color(Color::new(0.3, 0.4, 0.6))
+                .with_specular(0.0),
+        );
+
+    let chrome_sphere = Object::new_sphere()
+        .set_transform(Matrix::id().translate(0.0, 1.0, 0.5))
+        .set_material(
+            &Material::new()
+                .with_color(Color::new(0.1, 0.1, 0.1))
+                .with_diffuse(0.2)
+                .with_specular(1.0)
+                .with_shininess(300.0)
+                .with_reflective(0.9),
+        );
+
+    let red_sphere = Object::new_sphere()
+        .set_transform(Matrix::id().scale(0.6, 0.6, 0.6).translate(-2.0, 0.6, -0.5))
+        .set_material(
+            &Material::new()
+                .with_color(Color::new(0.8, 0.1, 0.1))
+                .with_diffuse(0.7)
+                .with_specular(0.6)
+                .with_shininess(200.0)
+                .with_reflective(0.3),
+        );
+
+    let green_sphere = Object::new_sphere()
+        .set_transform(Matrix::id().scale(0.4, 0.4, 0.4).translate(1.8, 0.4, -1.0))
+        .set_material(
+            &Material::new()
+                .with_color(Color::new(0.1, 0.7, 0.2))
+                .with_diffuse(0.7)
+                .with_specular(0.6)
+                .with_shininess(200.0)
+                .with_reflective(0.3),
+        );
+
+    let light_source = PointLight::new(Color::new(1.0, 1.0, 1.0), Point::new(-6.0, 8.0, -8.0));
+    let world = World::new()
+        .with_objects(vec![floor, back_wall, chrome_sphere, red_sphere, green_sphere])
+        .with_lights(vec![Box::new(light_source)]);
+    let camera = Camera::new(
+        args.width,
+        args.height,
+        std::f64::consts::PI / 3.0,
+        view_transform(
+            Point::new(0.0, 2.0, -6.0),
+            Point::new(0.0, 0.8, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ),
+    );
+    let canvas = camera.render(&world);
+    canvas.save_as_ppm(&args.out).unwrap();
+}
@@ -0,0 +1,92 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderArgs {
+    pub width: usize,
+    pub height: usize,
+    pub out: String,
+}
+
+impl RenderArgs {
+    pub fn new(width: usize, height: usize, out: &str) -> Self {
+        RenderArgs {
+            width,
+            height,
+            out: out.to_string(),
+        }
+    }
+
+    // Overrides the defaults with any of --width, --height and --out found in args.
+    pub fn parse<I>(args: I, defaults: RenderArgs) -> Result<RenderArgs, String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut result = defaults;
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value for {flag}"))?;
+            match flag.as_str() {
+                "--width" => result.width = parse_size(&flag, &value)?,
+                "--height" => result.height = parse_size(&flag, &value)?,
+                "--out" => result.out = value,
+                _ => return Err(format!("Unknown argument {flag}")),
+            }
+        }
+        Ok(result)
+    }
+
+    // Parses the process arguments, exiting with a usage message if they are invalid.
+    pub fn from_env(defaults: RenderArgs) -> RenderArgs {
+        match RenderArgs::parse(std::env::args().skip(1), defaults) {
+            Ok(args) => args,
+            Err(message) => {
+                eprintln!("{message}");
+                eprintln!("Usage: [--width <pixels>] [--height <pixels>] [--out <path>]");
+                std::process::exit(2);
+            }
+        }
+    }
+}
+
+fn parse_size(flag: &str, value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(size) if size > 0 => Ok(size),
+        _ => Err(format!("{flag} expects a positive integer, got {value}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn no_arguments_keeps_defaults() {
+        let defaults = RenderArgs::new(2000, 1000, "samples/chapter_7");
+        let parsed = RenderArgs::parse(args(&[]), defaults.clone()).unwrap();
+        assert_eq!(parsed, defaults);
+    }
+
+    #[test]
+    fn arguments_override_defaults() {
+        let defaults = RenderArgs::new(2000, 1000, "samples/chapter_7");
+        let parsed = RenderArgs::parse(
+            args(&["--height", "300", "--out", "preview", "--width", "400"]),
+            defaults,
+        )
+        .unwrap();
+        assert_eq!(parsed, RenderArgs::new(400, 300, "preview"));
+    }
+
+    #[test]
+    fn invalid_arguments_are_errors() {
+        let defaults = RenderArgs::new(10, 10, "out");
+        assert!(RenderArgs::parse(args(&["--width"]), defaults.clone()).is_err());
+        assert!(RenderArgs::parse(args(&["--width", "abc"]), defaults.clone()).is_err());
+        assert!(RenderArgs::parse(args(&["--width", "0"]), defaults.clone()).is_err());
+        assert!(RenderArgs::parse(args(&["--depth", "3"]), defaults).is_err());
+    }
+}
@@ -17,6 +17,7 @@ impl ApproxEq for f64 {
     }
 
     fn approx_eq_epsilon(self, other: Self, epsilon: f64) -> bool {
-        self.abs_diff_eq(&other, epsilon)
+        // equal infinities differ by NaN, so compare them directly
+        self == other || self.abs_diff_eq(&other, epsilon)
     }
 }
@@ -1,4 +1,5 @@
 #![allow(dead_code)]
+pub mod cli;
 pub mod primitives {
     pub use canvas::Canvas;
     pub use color::Color;
@@ -12,11 +13,14 @@ pub mod primitives {
     mod matrix2;
     mod matrix3;
     mod point;
+    mod quaternion;
     mod tuple;
     mod vector;
 }
 pub mod rtc {
+    pub mod bounds;
     pub mod camera;
+    pub mod config;
     pub mod intersection;
     pub mod light;
     pub mod material;
@@ -26,16 +30,22 @@ pub mod rtc {
     pub mod transformation;
     pub mod world;
     pub mod pattern;
+    pub mod quality;
+    pub mod uv;
     pub mod shapes {
         pub mod plane;
         pub mod sphere;
         pub mod cube;
         pub mod cylinder;
         pub mod cone;
+        pub mod disk;
+        pub mod mesh;
     }
 }
+pub use float::rng::Rng;
 mod float {
     pub mod approx_eq;
     pub mod epsilon;
+    pub mod rng;
     pub use approx_eq::ApproxEq;
 }
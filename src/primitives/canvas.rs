@@ -1,11 +1,96 @@
 use crate::primitives::color::Color;
 use std::fs::File;
-use std::io::prelude::*;
+use std::io::{prelude::*, BufWriter};
+#[derive(Debug, PartialEq)]
+pub struct OutOfBounds {
+    pub x: usize,
+    pub y: usize,
+}
+
+impl std::fmt::Display for OutOfBounds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Pixel out of bounds - {}, {}", self.x, self.y)
+    }
+}
+
+impl std::error::Error for OutOfBounds {}
+
+#[derive(Debug, PartialEq)]
+pub struct SizeMismatch {
+    pub expected: (usize, usize), // (width, length)
+    pub found: (usize, usize),
+}
+
+impl std::fmt::Display for SizeMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Canvas size mismatch - expected {}x{}, found {}x{}",
+            self.expected.0, self.expected.1, self.found.0, self.found.1
+        )
+    }
+}
+
+impl std::error::Error for SizeMismatch {}
+
+#[derive(Debug, PartialEq)]
+pub struct NotDivisible {
+    pub size: (usize, usize), // (width, length)
+    pub factor: usize,
+}
+
+impl std::fmt::Display for NotDivisible {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Canvas {}x{} cannot be downscaled by {}",
+            self.size.0, self.size.1, self.factor
+        )
+    }
+}
+
+impl std::error::Error for NotDivisible {}
+
+// Reconstruction filter used when downscaling a supersampled canvas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DownscaleFilter {
+    Box,      // plain average of each factor x factor block
+    Triangle, // tent reaching one output pixel into the neighbours; smoother edges
+    Gaussian, // sigma of half an output pixel, cut off at 1.5 output pixels
+}
+
+impl DownscaleFilter {
+    // Half-width of the filter, in source pixels.
+    fn radius(&self, factor: f64) -> f64 {
+        match self {
+            DownscaleFilter::Box => factor / 2.0,
+            DownscaleFilter::Triangle => factor,
+            DownscaleFilter::Gaussian => factor * 1.5,
+        }
+    }
+
+    // Weight of a source pixel whose center is distance away from the output pixel's center.
+    fn weight(&self, distance: f64, factor: f64) -> f64 {
+        match self {
+            DownscaleFilter::Box => 1.0,
+            DownscaleFilter::Triangle => (1.0 - distance.abs() / factor).max(0.0),
+            DownscaleFilter::Gaussian => {
+                let sigma = factor / 2.0;
+                (-distance * distance / (2.0 * sigma * sigma)).exp()
+            }
+        }
+    }
+}
+
+// Bayer matrix ranks for ordered dithering.
+const BAYER_4X4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
 #[derive(Debug)]
 pub struct Canvas {
     width: usize,
     length: usize,
     grid: Vec<Vec<Color>>,
+    alpha: Vec<Vec<f64>>, // coverage per pixel, 1 is opaque; not written to PPM
 }
 impl Canvas {
     pub fn new(width: usize, length: usize) -> Canvas {
@@ -13,6 +98,7 @@ impl Canvas {
             width,
             length,
             grid: vec![vec![Color::new(0.0, 0.0, 0.0); width]; length],
+            alpha: vec![vec![1.0; width]; length],
         }
     }
 
@@ -35,19 +121,204 @@ impl Canvas {
         self.grid[height][width]
     }
 
+    pub fn write_pixel_with_alpha(&mut self, x: usize, y: usize, color: Color, alpha: f64) {
+        self.write_pixel(x, y, color);
+        self.alpha[y][x] = alpha;
+    }
+
+    pub fn alpha_at(&self, x: usize, y: usize) -> f64 {
+        self.alpha[y][x]
+    }
+
+    // Porter-Duff "over" with top drawn in front; colors are not premultiplied by alpha.
+    pub fn composite_over(&mut self, top: &Canvas) -> Result<(), SizeMismatch> {
+        if top.width != self.width || top.length != self.length {
+            return Err(SizeMismatch {
+                expected: (self.width, self.length),
+                found: (top.width, top.length),
+            });
+        }
+        for y in 0..self.length {
+            for x in 0..self.width {
+                let (top_alpha, bottom_alpha) = (top.alpha[y][x], self.alpha[y][x]);
+                let bottom_weight = bottom_alpha * (1.0 - top_alpha);
+                let alpha = top_alpha + bottom_weight;
+                self.grid[y][x] = if alpha > 0.0 {
+                    (top.grid[y][x] * top_alpha + self.grid[y][x] * bottom_weight) * (1.0 / alpha)
+                } else {
+                    Color::black()
+                };
+                self.alpha[y][x] = alpha;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn try_write_pixel(&mut self, x: usize, y: usize, color: Color) -> Result<(), OutOfBounds> {
+        let pixel = self
+            .grid
+            .get_mut(y)
+            .and_then(|row| row.get_mut(x))
+            .ok_or(OutOfBounds { x, y })?;
+        *pixel = color;
+        Ok(())
+    }
+
+    pub fn try_pixel_at(&self, x: usize, y: usize) -> Option<Color> {
+        self.grid.get(y).and_then(|row| row.get(x)).copied()
+    }
+
+    pub fn crop(&self, x: usize, y: usize, width: usize, length: usize) -> Canvas {
+        if x + width > self.width || y + length > self.length {
+            panic!("Crop out of bounds - {x}, {y}, {width}x{length}");
+        }
+        Canvas {
+            width,
+            length,
+            grid: self.grid[y..y + length]
+                .iter()
+                .map(|row| row[x..x + width].to_vec())
+                .collect(),
+            alpha: self.alpha[y..y + length]
+                .iter()
+                .map(|row| row[x..x + width].to_vec())
+                .collect(),
+        }
+    }
+
+    // Shrinks by an integer factor, filtering separably in x and y. Samples past the edges are
+    // dropped and the remaining weights renormalised. Alpha is filtered the same way as color.
+    pub fn downscale(&self, factor: usize, filter: DownscaleFilter) -> Result<Canvas, NotDivisible> {
+        if factor == 0 || !self.width.is_multiple_of(factor) || !self.length.is_multiple_of(factor) {
+            return Err(NotDivisible {
+                size: (self.width, self.length),
+                factor,
+            });
+        }
+        let f = factor as f64;
+        let taps = |center: f64, size: usize| -> Vec<(usize, f64)> {
+            let radius = filter.radius(f);
+            let first = (center - radius).floor().max(0.0) as usize;
+            let last = ((center + radius).ceil() as usize).min(size);
+            (first..last)
+                .filter_map(|i| {
+                    let distance = i as f64 + 0.5 - center;
+                    // the box is half-open so neighbouring blocks never share a pixel
+                    let inside = match filter {
+                        DownscaleFilter::Box => distance >= -radius && distance < radius,
+                        _ => distance.abs() < radius,
+                    };
+                    inside.then(|| (i, filter.weight(distance, f)))
+                })
+                .collect()
+        };
+        let mut result = Canvas::new(self.width / factor, self.length / factor);
+        for oy in 0..result.length {
+            let rows = taps((oy as f64 + 0.5) * f, self.length);
+            for ox in 0..result.width {
+                let columns = taps((ox as f64 + 0.5) * f, self.width);
+                let mut color = Color::black();
+                let mut alpha = 0.0;
+                let mut total = 0.0;
+                for &(y, wy) in &rows {
+                    for &(x, wx) in &columns {
+                        let w = wx * wy;
+                        color = color + self.grid[y][x] * w;
+                        alpha += self.alpha[y][x] * w;
+                        total += w;
+                    }
+                }
+                result.grid[oy][ox] = color * (1.0 / total);
+                result.alpha[oy][ox] = alpha / total;
+            }
+        }
+        Ok(result)
+    }
+
+    // Bresenham's line; pixels that fall outside the canvas are skipped instead of panicking.
+    pub fn draw_line(&mut self, x0: i64, y0: i64, x1: i64, y1: i64, color: Color) {
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let step_x = if x0 < x1 { 1 } else { -1 };
+        let step_y = if y0 < y1 { 1 } else { -1 };
+        let (mut x, mut y) = (x0, y0);
+        let mut error = dx + dy;
+        loop {
+            if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.length {
+                self.grid[y as usize][x as usize] = color;
+            }
+            if x == x1 && y == y1 {
+                break;
+            }
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    pub fn tone_map_reinhard(&mut self) {
+        self.tone_map_reinhard_with_exposure(1.0);
+    }
+
+    // Maps each channel c to c / (1 + c) after scaling by the exposure.
+    pub fn tone_map_reinhard_with_exposure(&mut self, exposure: f64) {
+        let reinhard = |c: f64| {
+            let c = c * exposure;
+            c / (1.0 + c)
+        };
+        for pixel in self.grid.iter_mut().flatten() {
+            *pixel = pixel.map(reinhard);
+        }
+    }
+
     pub fn to_ppm(&self) -> String {
-        let mut ppm = String::new();
-        ppm.push_str("P3\n");
-        ppm.push_str(&format!("{} {}\n", self.width, self.length));
-        ppm.push_str("255\n");
-        for row in self.grid.iter() {
+        let mut buffer = Vec::new();
+        self.write_ppm(&mut buffer).expect("Writing to a Vec never fails");
+        String::from_utf8(buffer).expect("PPM output is always ASCII")
+    }
+
+    pub fn write_ppm<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.write_ppm_quantized(writer, |c, _, _| (c * 255.0) as u8)
+    }
+
+    // Like to_ppm, but adds a 4x4 ordered dither before quantizing so smooth gradients don't band.
+    pub fn to_ppm_dithered(&self) -> String {
+        let mut buffer = Vec::new();
+        self.write_ppm_dithered(&mut buffer).expect("Writing to a Vec never fails");
+        String::from_utf8(buffer).expect("PPM output is always ASCII")
+    }
+
+    pub fn write_ppm_dithered<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        // thresholds in [0, 1) averaging 0.5, so rounding down after adding keeps the mean
+        self.write_ppm_quantized(writer, |c, x, y| {
+            let threshold = (BAYER_4X4[y % 4][x % 4] as f64 + 0.5) / 16.0;
+            (c * 255.0 + threshold) as u8
+        })
+    }
+
+    fn write_ppm_quantized<W: Write>(
+        &self,
+        writer: &mut W,
+        quantize: impl Fn(f64, usize, usize) -> u8,
+    ) -> std::io::Result<()> {
+        writer.write_all(b"P3\n")?;
+        writer.write_all(format!("{} {}\n", self.width, self.length).as_bytes())?;
+        writer.write_all(b"255\n")?;
+        for (y, row) in self.grid.iter().enumerate() {
+            let mut ppm = String::new();
             let mut row_str = String::new();
-            for pixel in row.iter() {
+            for (x, pixel) in row.iter().enumerate() {
                 let s = format!(
                     "{} {} {} ",
-                    (pixel.red() * 255.0) as u8,
-                    (pixel.green() * 255.0) as u8,
-                    (pixel.blue() * 255.0) as u8
+                    quantize(pixel.red(), x, y),
+                    quantize(pixel.green(), x, y),
+                    quantize(pixel.blue(), x, y)
                 );
                 if row_str.len() + s.len() > 70 {
                     ppm.push_str(row_str.trim());
@@ -59,15 +330,16 @@ impl Canvas {
 
             ppm.push_str(row_str.trim());
             ppm.push('\n');
+            writer.write_all(ppm.as_bytes())?;
         }
-        ppm
+        Ok(())
     }
 
     pub fn save_as_ppm(&self, filename: &str) -> std::io::Result<()> {
         let filename = format!("{}.ppm", filename);
-        let mut file = File::create(filename)?;
-        file.write_all(self.to_ppm().as_bytes())?;
-        Ok(())
+        let mut file = BufWriter::new(File::create(filename)?);
+        self.write_ppm(&mut file)?;
+        file.flush()
     }
 }
 #[cfg(test)]
@@ -93,6 +365,23 @@ mod tests {
         assert_eq!(canvas.pixel_at(2, 3), red);
     }
 
+    #[test]
+    fn reinhard_tone_mapping() {
+        let mut canvas = Canvas::new(2, 1);
+        canvas.write_pixel(0, 0, Color::new(3.0, 0.0, 1.0));
+        canvas.tone_map_reinhard();
+        assert_eq!(canvas.pixel_at(0, 0), Color::new(0.75, 0.0, 0.5));
+        assert_eq!(canvas.pixel_at(1, 0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn reinhard_tone_mapping_with_exposure() {
+        let mut canvas = Canvas::new(1, 1);
+        canvas.write_pixel(0, 0, Color::new(1.5, 0.5, 0.0));
+        canvas.tone_map_reinhard_with_exposure(2.0);
+        assert_eq!(canvas.pixel_at(0, 0), Color::new(0.75, 0.5, 0.0));
+    }
+
     #[test]
     fn canvas_to_ppm() {
         let canvas = Canvas::new(5, 3);
@@ -101,6 +390,16 @@ mod tests {
         assert_eq!(ppm, expected);
     }
 
+    #[test]
+    fn write_ppm_to_buffer() {
+        let mut canvas = Canvas::new(5, 3);
+        canvas.write_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        canvas.write_pixel(4, 2, Color::new(0.0, 0.5, 1.0));
+        let mut buffer: Vec<u8> = Vec::new();
+        canvas.write_ppm(&mut buffer).unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(), canvas.to_ppm());
+    }
+
     #[test]
     fn ppm_header() {
         let canvas = Canvas::new(5, 3);
@@ -122,4 +421,155 @@ mod tests {
         let expected = "P3\n5 3\n255\n255 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n0 0 0 0 0 0 0 127 0 0 0 0 0 0 0\n0 0 0 0 0 0 0 0 0 0 0 0 0 0 255\n";
         assert_eq!(ppm, expected);
     }
+
+    #[test]
+    fn draw_line_diagonal_is_clipped() {
+        let mut c = Canvas::new(4, 4);
+        let red = Color::new(1.0, 0.0, 0.0);
+        c.draw_line(-2, -2, 5, 5, red);
+        for x in 0..4 {
+            for y in 0..4 {
+                let expected = if x == y { red } else { Color::black() };
+                assert_eq!(c.pixel_at(x, y), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn draw_line_shallow_slope() {
+        let mut c = Canvas::new(7, 3);
+        let white = Color::white();
+        c.draw_line(0, 0, 6, 2, white);
+        let lit: Vec<(usize, usize)> = (0..7)
+            .flat_map(|x| (0..3).map(move |y| (x, y)))
+            .filter(|(x, y)| c.pixel_at(*x, *y) == white)
+            .collect();
+        assert_eq!(lit, vec![(0, 0), (1, 0), (2, 1), (3, 1), (4, 1), (5, 2), (6, 2)]);
+    }
+
+    #[test]
+    fn crop_copies_sub_rectangle() {
+        let mut c = Canvas::new(4, 3);
+        for x in 0..4 {
+            for y in 0..3 {
+                c.write_pixel(x, y, Color::new(x as f64, y as f64, 0.0));
+            }
+        }
+        let cropped = c.crop(1, 1, 2, 2);
+        assert_eq!(cropped.width(), 2);
+        assert_eq!(cropped.length(), 2);
+        assert_eq!(cropped.pixel_at(0, 0), Color::new(1.0, 1.0, 0.0));
+        assert_eq!(cropped.pixel_at(1, 0), Color::new(2.0, 1.0, 0.0));
+        assert_eq!(cropped.pixel_at(0, 1), Color::new(1.0, 2.0, 0.0));
+        assert_eq!(cropped.pixel_at(1, 1), Color::new(2.0, 2.0, 0.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn crop_out_of_bounds_panics() {
+        Canvas::new(4, 3).crop(3, 0, 2, 2);
+    }
+
+    #[test]
+    fn composite_half_transparent_red_over_blue() {
+        let mut background = Canvas::new(2, 2);
+        let mut layer = Canvas::new(2, 2);
+        for x in 0..2 {
+            for y in 0..2 {
+                background.write_pixel(x, y, Color::new(0.0, 0.0, 1.0));
+                layer.write_pixel_with_alpha(x, y, Color::new(1.0, 0.0, 0.0), 0.5);
+            }
+        }
+        layer.write_pixel_with_alpha(1, 1, Color::white(), 0.0);
+        background.composite_over(&layer).unwrap();
+        assert_eq!(background.pixel_at(0, 0), Color::new(0.5, 0.0, 0.5));
+        assert_eq!(background.alpha_at(0, 0), 1.0);
+        assert_eq!(background.pixel_at(1, 1), Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn composite_over_rejects_other_sizes() {
+        let mut background = Canvas::new(2, 2);
+        assert_eq!(
+            background.composite_over(&Canvas::new(3, 2)),
+            Err(SizeMismatch { expected: (2, 2), found: (3, 2) })
+        );
+    }
+
+    fn sharp_edge() -> Canvas {
+        let mut c = Canvas::new(8, 4);
+        for x in 4..8 {
+            for y in 0..4 {
+                c.write_pixel(x, y, Color::white());
+            }
+        }
+        c
+    }
+
+    #[test]
+    fn box_downscale_keeps_sharp_edge() {
+        let small = sharp_edge().downscale(2, DownscaleFilter::Box).unwrap();
+        assert_eq!((small.width(), small.length()), (4, 2));
+        assert_eq!(small.pixel_at(1, 0), Color::black());
+        assert_eq!(small.pixel_at(2, 0), Color::white());
+    }
+
+    #[test]
+    fn triangle_downscale_softens_sharp_edge() {
+        let edge = sharp_edge();
+        let boxed = edge.downscale(2, DownscaleFilter::Box).unwrap();
+        let tent = edge.downscale(2, DownscaleFilter::Triangle).unwrap();
+        assert_eq!(tent.pixel_at(1, 1), Color::new(0.125, 0.125, 0.125));
+        assert_eq!(tent.pixel_at(2, 1), Color::new(0.875, 0.875, 0.875));
+        // away from the edge both filters agree
+        assert_eq!(tent.pixel_at(0, 0), boxed.pixel_at(0, 0));
+        assert_eq!(tent.pixel_at(3, 0), boxed.pixel_at(3, 0));
+        let smooth = edge.downscale(2, DownscaleFilter::Gaussian).unwrap();
+        assert!(smooth.pixel_at(1, 0).red() > 0.0 && smooth.pixel_at(1, 0).red() < 0.5);
+    }
+
+    #[test]
+    fn downscale_rejects_indivisible_factor() {
+        let c = Canvas::new(8, 6);
+        assert_eq!(
+            c.downscale(4, DownscaleFilter::Box).unwrap_err(),
+            NotDivisible { size: (8, 6), factor: 4 }
+        );
+        assert!(c.downscale(0, DownscaleFilter::Box).is_err());
+    }
+
+    #[test]
+    fn try_write_and_read_pixels() {
+        let mut c = Canvas::new(3, 2);
+        let red = Color::new(1.0, 0.0, 0.0);
+        assert_eq!(c.try_write_pixel(2, 1, red), Ok(()));
+        assert_eq!(c.try_pixel_at(2, 1), Some(red));
+        assert_eq!(c.try_write_pixel(3, 1, red), Err(OutOfBounds { x: 3, y: 1 }));
+        assert_eq!(c.try_write_pixel(0, 2, red), Err(OutOfBounds { x: 0, y: 2 }));
+        assert_eq!(c.try_pixel_at(3, 0), None);
+        assert_eq!(c.try_pixel_at(0, 2), None);
+    }
+
+    #[test]
+    fn dithered_gray_keeps_its_average() {
+        let mut canvas = Canvas::new(4, 4);
+        for y in 0..4 {
+            for x in 0..4 {
+                canvas.write_pixel(x, y, Color::new(0.5, 0.5, 0.5));
+            }
+        }
+        let ppm = canvas.to_ppm_dithered();
+        assert_eq!(ppm, canvas.to_ppm_dithered());
+        let values: Vec<f64> = ppm
+            .lines()
+            .skip(3)
+            .flat_map(|line| line.split(' '))
+            .map(|v| v.parse().unwrap())
+            .collect();
+        assert_eq!(values.len(), 48);
+        assert!(values.iter().all(|v| *v == 127.0 || *v == 128.0));
+        assert!(values.contains(&127.0) && values.contains(&128.0));
+        let average = values.iter().sum::<f64>() / values.len() as f64;
+        assert_eq!(average, 127.5);
+    }
 }
@@ -1,5 +1,6 @@
-use crate::float::ApproxEq;
+use crate::float::{epsilon::LOW_EPSILON, ApproxEq};
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 pub struct Color {
     r: f64,
     g: f64,
@@ -23,6 +24,24 @@ impl Color {
         self.b
     }
 
+    pub fn from_channels(channels: [f64; 3]) -> Self {
+        Color::new(channels[0], channels[1], channels[2])
+    }
+
+    pub fn channels(&self) -> [f64; 3] {
+        [self.r, self.g, self.b]
+    }
+
+    pub fn map(&self, f: impl Fn(f64) -> f64) -> Color {
+        Color::from_channels(self.channels().map(f))
+    }
+
+    pub fn approx_eq_epsilon(&self, other: &Color, epsilon: f64) -> bool {
+        self.r.approx_eq_epsilon(other.r, epsilon)
+            && self.g.approx_eq_epsilon(other.g, epsilon)
+            && self.b.approx_eq_epsilon(other.b, epsilon)
+    }
+
     pub fn black() -> Self {
         Color::new(0.0, 0.0, 0.0)
     }
@@ -30,24 +49,32 @@ impl Color {
     pub fn white() -> Self {
         Color::new(1.0, 1.0, 1.0)
     }
+
+    // Rec. 709 weights.
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    // Unlike + and -, these clamp every channel to [0, 1].
+    pub fn saturating_add(&self, other: Color) -> Color {
+        (*self + other).map(|c| c.clamp(0.0, 1.0))
+    }
+
+    pub fn saturating_sub(&self, other: Color) -> Color {
+        (*self - other).map(|c| c.clamp(0.0, 1.0))
+    }
 }
 
 impl PartialEq for Color {
     fn eq(&self, other: &Self) -> bool {
-        self.r.approx_eq_low_precision(other.r)
-            && self.g.approx_eq_low_precision(other.g)
-            && self.b.approx_eq_low_precision(other.b)
+        self.approx_eq_epsilon(other, LOW_EPSILON)
     }
 }
 
 impl std::ops::Mul<f64> for Color{
     type Output = Color;
     fn mul(self, rhs: f64) -> Self::Output {
-        Color{
-            r: self.r * rhs,
-            g: self.g * rhs,
-            b: self.b * rhs,
-        } 
+        self.map(|c| c * rhs)
     }
 }
 
@@ -108,6 +135,27 @@ mod tests {
         assert_eq!(result, Color::new(0.1, 0.36, 0.06));
     }
 
+    #[test]
+    fn channels_round_trip() {
+        let c = Color::new(0.1, 0.2, 0.3);
+        assert_eq!(c.channels(), [0.1, 0.2, 0.3]);
+        assert_eq!(Color::from_channels(c.channels()), c);
+    }
+
+    #[test]
+    fn map_applies_to_every_channel() {
+        let c = Color::new(0.1, 0.2, 0.3);
+        assert_eq!(c.map(|c| c * 2.0), Color::new(0.2, 0.4, 0.6));
+    }
+
+    #[test]
+    fn approx_eq_with_custom_epsilon() {
+        let a = Color::new(0.5, 0.5, 0.5);
+        let b = Color::new(0.5, 0.5, 0.5005);
+        assert!(a.approx_eq_epsilon(&b, 1e-3));
+        assert!(!a.approx_eq_epsilon(&b, 1e-9));
+    }
+
     #[test]
     fn sub() {
         let a = Color::new(1.0, 2.0, 3.0);
@@ -115,4 +163,20 @@ mod tests {
         let result = a - b;
         assert_eq!(result, Color::new(-2.0, -2.0, -2.0));
     }
+
+    #[test]
+    fn saturating_ops_stay_in_range() {
+        assert_eq!(Color::white().saturating_add(Color::white()), Color::white());
+        assert_eq!(Color::black().saturating_sub(Color::white()), Color::black());
+        let c = Color::new(0.75, 0.25, 0.5).saturating_add(Color::new(0.5, 0.25, 0.0));
+        assert_eq!(c, Color::new(1.0, 0.5, 0.5));
+    }
+
+    #[test]
+    fn luminance_weights_green_most() {
+        let green = Color::new(0.0, 1.0, 0.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+        assert!(green.luminance() > blue.luminance());
+        assert!(Color::white().luminance().approx_eq(1.0));
+    }
 }
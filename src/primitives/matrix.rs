@@ -1,10 +1,11 @@
 use crate::{
     float::ApproxEq,
-    primitives::{matrix3::Matrix3, tuple::Tuple},
+    primitives::{matrix3::Matrix3, quaternion::Quaternion, tuple::Tuple, vector::Vector},
 };
 use std::ops::{Index, IndexMut};
 const MATRIX_SIZE: usize = 4;
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 pub struct Matrix {
     grid: [f64; MATRIX_SIZE * MATRIX_SIZE],
 }
@@ -57,6 +58,14 @@ impl Matrix {
         result
     }
 
+    pub fn transpose_in_place(&mut self) {
+        for i in 0..MATRIX_SIZE {
+            for j in (i + 1)..MATRIX_SIZE {
+                self.grid.swap(i * MATRIX_SIZE + j, j * MATRIX_SIZE + i);
+            }
+        }
+    }
+
     pub fn submatrix(&self, row: usize, col: usize) -> Matrix3 {
         let mut result = Matrix3::new();
         let mut result_row = 0;
@@ -79,7 +88,7 @@ impl Matrix {
     }
 
     pub fn cofactor(&self, row: usize, col: usize) -> f64 {
-        if (row + col) % 2 == 0 {
+        if (row + col).is_multiple_of(2) {
             self.submatrix(row, col).determinant()
         } else {
             -self.submatrix(row, col).determinant()
@@ -162,6 +171,46 @@ impl Matrix {
         result[(2, 1)] = zy;
         result * *self
     }
+
+    /// Splits an affine transform into `(translation, rotation, scale)` such that
+    /// `self == translation * rotation * scale`. Shear is not recovered: a sheared
+    /// matrix yields a rotation that is not orthonormal.
+    pub fn decompose(&self) -> (Vector, Matrix, Vector) {
+        let translation = Vector::new(self[(0, 3)], self[(1, 3)], self[(2, 3)]);
+        let column = |j: usize| Vector::new(self[(0, j)], self[(1, j)], self[(2, j)]);
+        let mut scale = Vector::new(
+            column(0).magnitude(),
+            column(1).magnitude(),
+            column(2).magnitude(),
+        );
+        if self.determinant() < 0.0 {
+            // a mirror is folded into the x scale so the rotation stays proper
+            scale = Vector::new(-scale.x(), scale.y(), scale.z());
+        }
+        let mut rotation = Matrix::id();
+        for i in 0..3 {
+            rotation[(i, 0)] = self[(i, 0)] / scale.x();
+            rotation[(i, 1)] = self[(i, 1)] / scale.y();
+            rotation[(i, 2)] = self[(i, 2)] / scale.z();
+        }
+        (translation, rotation, scale)
+    }
+
+    // Blends translation and scale linearly and rotation along the shortest arc.
+    pub fn lerp(&self, other: &Matrix, t: f64) -> Matrix {
+        let (translation_a, rotation_a, scale_a) = self.decompose();
+        let (translation_b, rotation_b, scale_b) = other.decompose();
+        let translation = translation_a + (translation_b - translation_a) * t;
+        let scale = scale_a + (scale_b - scale_a) * t;
+        let rotation = Quaternion::from_rotation(&rotation_a)
+            .slerp(&Quaternion::from_rotation(&rotation_b), t)
+            .to_rotation();
+        (rotation * Matrix::id().scale(scale.x(), scale.y(), scale.z())).translate(
+            translation.x(),
+            translation.y(),
+            translation.z(),
+        )
+    }
 }
 
 impl std::ops::Mul<Matrix> for Matrix {
@@ -180,6 +229,31 @@ impl std::ops::Mul<Matrix> for Matrix {
     }
 }
 
+impl From<&Matrix> for Matrix {
+    fn from(matrix: &Matrix) -> Matrix {
+        *matrix
+    }
+}
+
+// Rows in order, so rows[i][j] lands at (i, j).
+impl From<[[f64; MATRIX_SIZE]; MATRIX_SIZE]> for Matrix {
+    fn from(rows: [[f64; MATRIX_SIZE]; MATRIX_SIZE]) -> Matrix {
+        let mut matrix = Matrix::new();
+        for (i, row) in rows.iter().enumerate() {
+            for (j, value) in row.iter().enumerate() {
+                matrix[(i, j)] = *value;
+            }
+        }
+        matrix
+    }
+}
+
+impl std::ops::MulAssign<Matrix> for Matrix {
+    fn mul_assign(&mut self, rhs: Matrix) {
+        *self = *self * rhs;
+    }
+}
+
 impl<T> std::ops::Mul<T> for Matrix
 where
     T: Tuple,
@@ -245,6 +319,27 @@ mod tests {
         assert_eq!(a * b, a);
     }
 
+    #[test]
+    fn matrix_from_nested_rows() {
+        let id = Matrix::from([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        assert_eq!(id, Matrix::id());
+        let m: Matrix = [
+            [1.0, 2.0, 3.0, 4.0],
+            [5.5, 6.5, 7.5, 8.5],
+            [9.0, 10.0, 11.0, 12.0],
+            [13.5, 14.5, 15.5, 16.5],
+        ]
+        .into();
+        assert_eq!(m[(0, 3)], 4.0);
+        assert_eq!(m[(1, 2)], 7.5);
+        assert_eq!(m[(3, 0)], 13.5);
+    }
+
     #[test]
     fn test_inverse() {
         let mut a = Matrix::new();
@@ -353,6 +448,52 @@ mod tests {
         assert_eq!(transform * p, Point::new(2.0, 3.0, 7.0));
     }
 
+    #[test]
+    fn decompose_and_recompose() {
+        let m = Matrix::id()
+            .translate(1.0, 2.0, 3.0)
+            .scale(2.0, 2.0, 2.0)
+            .rotate_y(std::f64::consts::FRAC_PI_2);
+        let (translation, rotation, scale) = m.decompose();
+        assert_eq!(translation, Vector::new(6.0, 4.0, -2.0));
+        assert_eq!(scale, Vector::new(2.0, 2.0, 2.0));
+        assert_eq!(rotation, Matrix::id().rotate_y(std::f64::consts::FRAC_PI_2));
+        let recomposed = (rotation * Matrix::id().scale(scale.x(), scale.y(), scale.z()))
+            .translate(translation.x(), translation.y(), translation.z());
+        assert_eq!(recomposed, m);
+    }
+
+    #[test]
+    fn decompose_mirror_keeps_rotation_proper() {
+        let m = Matrix::id().scale(-1.0, 2.0, 3.0);
+        let (_, rotation, scale) = m.decompose();
+        assert_eq!(scale, Vector::new(-1.0, 2.0, 3.0));
+        assert_eq!(rotation, Matrix::id());
+    }
+
+    #[test]
+    fn lerp_halfway_rotation_is_slerp() {
+        let a = Matrix::id();
+        let b = Matrix::id().rotate_y(std::f64::consts::FRAC_PI_2);
+        assert_eq!(a.lerp(&b, 0.5), Matrix::id().rotate_y(std::f64::consts::FRAC_PI_4));
+    }
+
+    #[test]
+    fn lerp_translation_and_scale() {
+        let a = Matrix::id();
+        let b = Matrix::id()
+            .scale(3.0, 3.0, 3.0)
+            .rotate_z(std::f64::consts::FRAC_PI_2)
+            .translate(4.0, -2.0, 0.0);
+        let halfway = Matrix::id()
+            .scale(2.0, 2.0, 2.0)
+            .rotate_z(std::f64::consts::FRAC_PI_4)
+            .translate(2.0, -1.0, 0.0);
+        assert_eq!(a.lerp(&b, 0.0), a);
+        assert_eq!(a.lerp(&b, 1.0), b);
+        assert_eq!(a.lerp(&b, 0.5), halfway);
+    }
+
     #[test]
     fn test_chain_transformations() {
         let p = Point::new(1.0, 0.0, 1.0);
@@ -373,4 +514,23 @@ mod tests {
             .translate(10.0, 5.0, 7.0);
         assert_eq!(chained * p, t * p);
     }
+
+    #[test]
+    fn mul_assign_matches_mul() {
+        let a = Matrix::id().rotate_x(0.4).translate(1.0, 2.0, 3.0);
+        let b = Matrix::id().scale(2.0, 3.0, 4.0).shear(1.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        let mut c = a;
+        c *= b;
+        assert_eq!(c, a * b);
+    }
+
+    #[test]
+    fn transpose_in_place_matches_transpose() {
+        let a = Matrix::from_array([
+            0.0, 9.0, 3.0, 0.0, 9.0, 8.0, 0.0, 8.0, 1.0, 8.0, 5.0, 3.0, 0.0, 0.0, 5.0, 8.0,
+        ]);
+        let mut b = a;
+        b.transpose_in_place();
+        assert_eq!(b, a.transpose());
+    }
 }
@@ -56,7 +56,7 @@ impl Matrix3 {
     }
 
     pub fn colfactor(&self, row: usize, col: usize) -> f64 {
-        if (row + col) % 2 == 0 {
+        if (row + col).is_multiple_of(2) {
             self.minor(row, col)
         } else {
             -self.minor(row, col)
@@ -1,14 +1,31 @@
 use crate::{
-    float::ApproxEq,
+    float::{epsilon::LOW_EPSILON, ApproxEq},
     primitives::{tuple::Tuple, vector::Vector},
 };
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point {
     x: f64,
     y: f64,
     z: f64,
 }
 
+impl Point {
+    pub fn min(&self, other: &Point) -> Point {
+        Point::new(self.x.min(other.x), self.y.min(other.y), self.z.min(other.z))
+    }
+
+    pub fn max(&self, other: &Point) -> Point {
+        Point::new(self.x.max(other.x), self.y.max(other.y), self.z.max(other.z))
+    }
+
+    pub fn approx_eq_epsilon(&self, other: &Point, epsilon: f64) -> bool {
+        self.x.approx_eq_epsilon(other.x, epsilon)
+            && self.y.approx_eq_epsilon(other.y, epsilon)
+            && self.z.approx_eq_epsilon(other.z, epsilon)
+    }
+}
+
 impl Tuple for Point {
     fn x(&self) -> f64 {
         self.x
@@ -36,9 +53,7 @@ impl Tuple for Point {
 
 impl PartialEq for Point {
     fn eq(&self, other: &Self) -> bool {
-        self.x.approx_eq_low_precision(other.x)
-            && self.y.approx_eq_low_precision(other.y)
-            && self.z.approx_eq_low_precision(other.z)
+        self.approx_eq_epsilon(other, LOW_EPSILON)
     }
 }
 
@@ -67,6 +82,18 @@ impl std::ops::Sub<Vector> for Point {
     }
 }
 
+impl std::ops::Index<usize> for Point {
+    type Output = f64;
+    fn index(&self, axis: usize) -> &f64 {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Point axis out of range - {axis}"),
+        }
+    }
+}
+
 impl std::ops::Mul<f64> for Point {
     type Output = Point;
     fn mul(self, rhs: f64) -> Self::Output {
@@ -77,6 +104,12 @@ impl std::ops::Mul<f64> for Point {
 mod tests {
     use super::*;
 
+    #[test]
+    fn index_by_axis() {
+        let p = Point::new(-1.0, 0.5, 4.0);
+        assert_eq!([p[0], p[1], p[2]], [-1.0, 0.5, 4.0]);
+    }
+
     #[test]
     fn add() {
         let p = Point::new(3.0, -2.0, 5.0);
@@ -99,6 +132,14 @@ mod tests {
         assert_eq!(p - v, result);
     }
 
+    #[test]
+    fn approx_eq_with_custom_epsilon() {
+        let a = Point::new(1.0, 2.0, 3.0);
+        let b = Point::new(1.0, 2.0005, 3.0);
+        assert!(a.approx_eq_epsilon(&b, 1e-3));
+        assert!(!a.approx_eq_epsilon(&b, 1e-9));
+    }
+
     #[test]
     fn scalar_multiplication() {
         let p = Point::new(1.0, -2.0, 3.0);
@@ -5,4 +5,36 @@ pub trait Tuple {
     fn y(&self) -> f64;
     fn z(&self) -> f64;
     fn w(&self) -> f64;
+
+    // Over x, y and z only, so a point is treated as its offset from the origin.
+    fn dot(&self, other: &Self) -> f64
+    where
+        Self: Sized,
+    {
+        self.x() * other.x() + self.y() * other.y() + self.z() * other.z()
+    }
+
+    fn magnitude(&self) -> f64
+    where
+        Self: Sized,
+    {
+        self.dot(self).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::{Point, Vector};
+
+    fn length<T: Tuple>(t: &T) -> f64 {
+        Tuple::magnitude(t)
+    }
+
+    #[test]
+    fn generic_magnitude_for_points_and_vectors() {
+        assert_eq!(length(&Vector::new(1.0, 2.0, 2.0)), 3.0);
+        assert_eq!(length(&Point::new(0.0, -3.0, 4.0)), 5.0);
+        assert_eq!(Point::new(1.0, 2.0, 3.0).dot(&Point::new(4.0, -5.0, 6.0)), 12.0);
+    }
 }
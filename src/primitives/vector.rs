@@ -1,5 +1,9 @@
-use crate::{float::ApproxEq, primitives::tuple::Tuple};
+use crate::{
+    float::{epsilon::LOW_EPSILON, ApproxEq},
+    primitives::tuple::Tuple,
+};
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vector {
     x: f64,
     y: f64,
@@ -8,7 +12,11 @@ pub struct Vector {
 
 impl Vector {
     pub fn magnitude(&self) -> f64 {
-        (self.x.powi(2) + self.y.powi(2) + self.z.powi(2)).sqrt()
+        self.magnitude_squared().sqrt()
+    }
+
+    pub fn magnitude_squared(&self) -> f64 {
+        self.x.powi(2) + self.y.powi(2) + self.z.powi(2)
     }
 
     pub fn normalize(&self) -> Vector {
@@ -34,6 +42,24 @@ impl Vector {
     pub fn reflect(&self, normal: &Vector) -> Vector {
         *self - *normal * 2.0 * self.dot_product(normal)
     }
+
+    pub fn min(&self, other: &Vector) -> Vector {
+        Vector::new(self.x.min(other.x), self.y.min(other.y), self.z.min(other.z))
+    }
+
+    pub fn max(&self, other: &Vector) -> Vector {
+        Vector::new(self.x.max(other.x), self.y.max(other.y), self.z.max(other.z))
+    }
+
+    pub fn abs(&self) -> Vector {
+        Vector::new(self.x.abs(), self.y.abs(), self.z.abs())
+    }
+
+    pub fn approx_eq_epsilon(&self, other: &Vector, epsilon: f64) -> bool {
+        self.x.approx_eq_epsilon(other.x, epsilon)
+            && self.y.approx_eq_epsilon(other.y, epsilon)
+            && self.z.approx_eq_epsilon(other.z, epsilon)
+    }
 }
 impl Tuple for Vector {
     fn x(&self) -> f64 {
@@ -60,11 +86,21 @@ impl Tuple for Vector {
     }
 }
 
+impl std::ops::Index<usize> for Vector {
+    type Output = f64;
+    fn index(&self, axis: usize) -> &f64 {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vector axis out of range - {axis}"),
+        }
+    }
+}
+
 impl PartialEq for Vector {
     fn eq(&self, other: &Self) -> bool {
-        self.x.approx_eq_low_precision(other.x)
-            && self.y.approx_eq_low_precision(other.y)
-            && self.z.approx_eq_low_precision(other.z)
+        self.approx_eq_epsilon(other, LOW_EPSILON)
     }
 }
 
@@ -115,6 +151,20 @@ impl std::ops::Mul<f64> for Vector {
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[test]
+    fn index_by_axis() {
+        let v = Vector::new(1.0, 2.0, 3.0);
+        assert_eq!(v[0], 1.0);
+        assert_eq!(v[1], 2.0);
+        assert_eq!(v[2], 3.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_past_z_panics() {
+        let _ = Vector::new(1.0, 2.0, 3.0)[3];
+    }
+
     #[test]
     fn vector() {
         let v = Vector::new(4.3, -4.2, 3.1);
@@ -180,6 +230,15 @@ mod tests {
         assert_eq!(b.cross_product(a), Vector::new(1.0, -2.0, 1.0));
     }
 
+    #[test]
+    fn approx_eq_with_custom_epsilon() {
+        let a = Vector::new(1.0, 2.0, 3.0);
+        let b = Vector::new(1.0005, 2.0, 3.0);
+        assert!(a.approx_eq_epsilon(&b, 1e-3));
+        assert!(!a.approx_eq_epsilon(&b, 1e-9));
+        assert_eq!(a, b);
+    }
+
     #[test]
     fn reflect() {
         let v = Vector::new(1.0, -1.0, 0.0);
@@ -195,4 +254,19 @@ mod tests {
         let r = v.reflect(&n);
         assert_eq!(r, Vector::new(1.0, 0.0, 0.0));
     }
+
+    #[test]
+    fn magnitude_squared_is_square_of_magnitude() {
+        let v = Vector::new(1.0, -2.0, 3.5);
+        assert!(v.magnitude_squared().approx_eq(v.magnitude().powi(2)));
+    }
+
+    #[test]
+    fn component_wise_min_max_abs() {
+        let a = Vector::new(1.0, -2.0, 3.0);
+        let b = Vector::new(0.0, 5.0, 2.0);
+        assert_eq!(a.max(&b), Vector::new(1.0, 5.0, 3.0));
+        assert_eq!(a.min(&b), Vector::new(0.0, -2.0, 2.0));
+        assert_eq!(Vector::new(-1.0, 2.0, -3.0).abs(), Vector::new(1.0, 2.0, 3.0));
+    }
 }
@@ -0,0 +1,151 @@
+use crate::primitives::{Matrix, Point, Tuple};
+use crate::rtc::ray::Ray;
+
+// Axis-aligned box; an infinite extent on an axis is allowed (planes, open cylinders).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoundingBox {
+    min: Point,
+    max: Point,
+}
+
+impl BoundingBox {
+    pub fn new(min: Point, max: Point) -> Self {
+        BoundingBox { min, max }
+    }
+
+    pub fn min(&self) -> Point {
+        self.min
+    }
+
+    pub fn max(&self) -> Point {
+        self.max
+    }
+
+    pub fn merge(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            min: self.min.min(&other.min),
+            max: self.max.max(&other.max),
+        }
+    }
+
+    // Box around this one after transforming it. Each output axis sums how far every input
+    // axis can push it, skipping zero entries so infinite extents don't turn into NaN.
+    pub fn transform(&self, m: &Matrix) -> BoundingBox {
+        let min = [self.min.x(), self.min.y(), self.min.z()];
+        let max = [self.max.x(), self.max.y(), self.max.z()];
+        let mut new_min = [0.0; 3];
+        let mut new_max = [0.0; 3];
+        for i in 0..3 {
+            new_min[i] = m[(i, 3)];
+            new_max[i] = m[(i, 3)];
+            for j in 0..3 {
+                let factor = m[(i, j)];
+                if factor == 0.0 {
+                    continue;
+                }
+                let (a, b) = (factor * min[j], factor * max[j]);
+                new_min[i] += a.min(b);
+                new_max[i] += a.max(b);
+            }
+        }
+        BoundingBox {
+            min: Point::new(new_min[0], new_min[1], new_min[2]),
+            max: Point::new(new_max[0], new_max[1], new_max[2]),
+        }
+    }
+
+    // Slab test: whether the ray passes through the box somewhere in [0, t_max]. Touching an
+    // edge counts as a hit, so this only ever rules out rays that cannot reach the box.
+    pub fn hit_by(&self, ray: &Ray, t_max: f64) -> bool {
+        let (origin, direction) = (ray.origin(), ray.direction());
+        let (mut t_near, mut t_far) = (0.0_f64, t_max);
+        for axis in 0..3 {
+            let (min, max) = (self.min[axis], self.max[axis]);
+            if direction[axis] == 0.0 {
+                if origin[axis] < min || origin[axis] > max {
+                    return false;
+                }
+                continue;
+            }
+            let a = (min - origin[axis]) / direction[axis];
+            let b = (max - origin[axis]) / direction[axis];
+            t_near = t_near.max(a.min(b));
+            t_far = t_far.min(a.max(b));
+            if t_near > t_far {
+                return false;
+            }
+        }
+        true
+    }
+
+    // A box that is flat along any axis has no volume, even if it is infinite along another.
+    pub fn volume(&self) -> f64 {
+        let extents = [
+            self.max.x() - self.min.x(),
+            self.max.y() - self.min.y(),
+            self.max.z() - self.min.z(),
+        ];
+        if extents.iter().any(|e| *e <= 0.0) {
+            return 0.0;
+        }
+        extents.iter().product()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volume_of_box() {
+        let b = BoundingBox::new(Point::new(-1.0, 0.0, 2.0), Point::new(1.0, 3.0, 6.0));
+        assert_eq!(b.volume(), 24.0);
+        let flat = BoundingBox::new(
+            Point::new(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY),
+            Point::new(f64::INFINITY, 0.0, f64::INFINITY),
+        );
+        assert_eq!(flat.volume(), 0.0);
+    }
+
+    #[test]
+    fn ray_hits_box_only_within_range() {
+        use crate::primitives::Vector;
+        let b = BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        let toward = Ray::new(Point::new(0.5, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(b.hit_by(&toward, f64::INFINITY));
+        assert!(!b.hit_by(&toward, 3.0));
+        let away = Ray::new(Point::new(0.5, 0.0, -5.0), Vector::new(0.0, 0.0, -1.0));
+        assert!(!b.hit_by(&away, f64::INFINITY));
+        let beside = Ray::new(Point::new(2.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(!b.hit_by(&beside, f64::INFINITY));
+        let floor = BoundingBox::new(
+            Point::new(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY),
+            Point::new(f64::INFINITY, 0.0, f64::INFINITY),
+        );
+        let down = Ray::new(Point::new(3.0, 2.0, 7.0), Vector::new(0.6, -0.8, 0.0));
+        assert!(floor.hit_by(&down, f64::INFINITY));
+        assert!(!floor.hit_by(&Ray::new(Point::new(3.0, 2.0, 7.0), Vector::new(0.6, 0.8, 0.0)), 10.0));
+    }
+
+    #[test]
+    fn merge_covers_both_boxes() {
+        let a = BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        let b = BoundingBox::new(Point::new(0.0, 2.0, -3.0), Point::new(4.0, 3.0, 0.0));
+        let merged = a.merge(&b);
+        assert_eq!(merged.min(), Point::new(-1.0, -1.0, -3.0));
+        assert_eq!(merged.max(), Point::new(4.0, 3.0, 1.0));
+    }
+
+    #[test]
+    fn transformed_box_encloses_rotated_corners() {
+        let b = BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        let m = Matrix::id()
+            .rotate_y(std::f64::consts::FRAC_PI_4)
+            .translate(1.0, 2.0, 3.0);
+        let t = b.transform(&m);
+        let r = 2.0_f64.sqrt();
+        assert_eq!(t.min(), Point::new(1.0 - r, 1.0, 3.0 - r));
+        assert_eq!(t.max(), Point::new(1.0 + r, 3.0, 3.0 + r));
+    }
+}
@@ -1,6 +1,53 @@
-use crate::primitives::{Matrix, Point, Tuple, Canvas};
-use crate::rtc::{ray::Ray, world::World};
+use crate::float::rng::Rng;
+use crate::primitives::{Matrix, Point, Tuple, Canvas, Color, Vector};
+use crate::rtc::{
+    bounds::BoundingBox, config::RenderConfig, quality::RenderQuality, ray::Ray,
+    transformation::{try_view_transform, ViewError},
+    world::{DebugTrace, World},
+};
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AntialiasMode {
+    None,
+    Grid(usize),                                 // n x n evenly spaced samples per pixel
+    // Random positions inside the pixel. A seed here overrides Camera::with_seed for the
+    // render; None uses the camera's seed.
+    Stochastic { samples: usize, seed: Option<u64> },
+    Adaptive { threshold: f64, max_depth: u8 },  // subdivide while corners disagree
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraError {
+    SingularTransform, // the transform has no (finite) inverse
+    View(ViewError),   // from try_view_transform
+}
+
+impl std::fmt::Display for CameraError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CameraError::SingularTransform => write!(f, "Camera transform is not invertible"),
+            CameraError::View(e) => write!(f, "Camera {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CameraError {}
+
+impl From<ViewError> for CameraError {
+    fn from(e: ViewError) -> Self {
+        CameraError::View(e)
+    }
+}
+
+// The inverse of a usable camera transform, or None when there isn't a finite one.
+fn finite_inverse(transform: &Matrix) -> Option<Matrix> {
+    let finite = |m: &Matrix| (0..4).all(|i| (0..4).all(|j| m[(i, j)].is_finite()));
+    transform.inverse().filter(|inverse| finite(transform) && finite(inverse))
+}
+
+#[derive(Clone)]
 pub struct Camera {
     hsize: usize,
     vsize: usize,
@@ -10,8 +57,17 @@ pub struct Camera {
     half_width: f64,
     half_height: f64,
     pixel_size: f64,
+    pixel_aspect: f64, // height / width of a single pixel
+    antialiasing: AntialiasMode,
+    seed: u64, // drives every random sample so renders are reproducible
+    config: RenderConfig,
+    aperture: f64, // lens radius; 0 is a pinhole with everything in focus
+    focal_distance: f64,
+    lens_samples: usize, // per antialiasing sample, only used while aperture > 0
 }
 
+const DEFAULT_SEED: u64 = 0x5EED;
+
 impl Camera {
     pub fn new(hsize: usize, vsize: usize, field_of_view: f64, transform: Matrix) -> Camera {
         let half_view = (field_of_view / 2.0).tan();
@@ -30,33 +86,301 @@ impl Camera {
             half_width,
             half_height,
             pixel_size: (half_width * 2.0) / (hsize as f64),
+            pixel_aspect: 1.0,
+            antialiasing: AntialiasMode::None,
+            seed: DEFAULT_SEED,
+            config: RenderConfig::default(),
+            aperture: 0.0,
+            focal_distance: 1.0,
+            lens_samples: 1,
         }
     }
-    
-    fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
-        let xoffset = (px as f64 + 0.5) * self.pixel_size;
-        let yoffset = (py as f64 + 0.5) * self.pixel_size;
 
-        let world_x = self.half_width - xoffset;
-        let world_y = self.half_height - yoffset;
+    // Like new, but returns an error instead of panicking or rendering NaNs for a bad transform.
+    pub fn try_new(
+        hsize: usize,
+        vsize: usize,
+        field_of_view: f64,
+        transform: Matrix,
+    ) -> Result<Camera, CameraError> {
+        match finite_inverse(&transform) {
+            Some(_) => Ok(Camera::new(hsize, vsize, field_of_view, transform)),
+            None => Err(CameraError::SingularTransform),
+        }
+    }
 
-        let pixel = self.transform_inverse * Point::new(world_x, world_y, -1.0);
+    pub fn with_antialiasing(mut self, mode: AntialiasMode) -> Self {
+        self.antialiasing = mode;
+        self
+    }
+
+    pub fn with_quality(self, quality: RenderQuality) -> Self {
+        self.with_antialiasing(quality.antialiasing())
+    }
+
+    pub fn with_config(mut self, config: RenderConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_pixel_aspect(mut self, pixel_aspect: f64) -> Self {
+        self.pixel_aspect = pixel_aspect;
+        self
+    }
+
+    // Points focal_distance in front of the camera stay sharp; the rest blur more the wider
+    // the aperture.
+    pub fn with_depth_of_field(mut self, aperture: f64, focal_distance: f64) -> Self {
+        self.aperture = aperture;
+        self.focal_distance = focal_distance;
+        self
+    }
+
+    // Lens positions traced for every antialiasing sample, so the rays per pixel multiply.
+    // The default of one gives each antialiasing sample its own lens position instead.
+    pub fn with_lens_samples(mut self, samples: usize) -> Self {
+        self.lens_samples = samples;
+        self
+    }
+
+    pub fn lens_samples(&self) -> usize {
+        if self.aperture > 0.0 {
+            self.lens_samples.max(1)
+        } else {
+            1
+        }
+    }
+
+    /// Returns the ray from the camera through the center of pixel `(px, py)`.
+    ///
+    /// ```
+    /// use ray_tracer::primitives::{Matrix, Point, Tuple, Vector};
+    /// use ray_tracer::rtc::camera::Camera;
+    ///
+    /// let camera = Camera::new(201, 101, std::f64::consts::FRAC_PI_2, Matrix::id());
+    /// let ray = camera.ray_for_pixel(100, 50);
+    /// assert_eq!(ray.origin(), Point::new(0.0, 0.0, 0.0));
+    /// assert_eq!(ray.direction(), Vector::new(0.0, 0.0, -1.0));
+    /// ```
+    pub fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
+        self.ray_through(px as f64 + 0.5, py as f64 + 0.5)
+    }
+
+    // Like ray_for_pixel, but for any position on the canvas measured in pixels.
+    fn ray_through(&self, x: f64, y: f64) -> Ray {
+        let pixel = self.transform_inverse * self.canvas_point(x, y);
         let origin = self.transform_inverse * Point::new(0.0, 0.0, 0.0);
 
         let direction = (pixel - origin).normalize();
         Ray::new(origin, direction)
     }
 
+    // Where a canvas position sits on the image plane at z = -1, in camera space.
+    fn canvas_point(&self, x: f64, y: f64) -> Point {
+        let xoffset = x * self.pixel_size;
+        let yoffset = y * self.pixel_size * self.pixel_aspect;
+
+        let world_x = self.half_width - xoffset;
+        let world_y = self.half_height * self.pixel_aspect - yoffset;
+        Point::new(world_x, world_y, -1.0)
+    }
+
+    // Thin lens: leaves from a random point on the aperture disk toward where the pinhole ray
+    // through (x, y) crosses the focal plane.
+    fn lens_ray_through(&self, x: f64, y: f64, rng: &mut Rng) -> Ray {
+        let focus = self.transform_inverse * (self.canvas_point(x, y) * self.focal_distance);
+        let r = self.aperture * rng.next_f64().sqrt();
+        let theta = 2.0 * std::f64::consts::PI * rng.next_f64();
+        let origin = self.transform_inverse * Point::new(r * theta.cos(), r * theta.sin(), 0.0);
+        Ray::new(origin, (focus - origin).normalize())
+    }
+
+    // Color seen through canvas position (x, y), averaged over the lens when it has an aperture.
+    fn sample_color(&self, world: &World, x: f64, y: f64, rng: &mut Rng) -> Color {
+        if self.aperture <= 0.0 {
+            return world.color_at_with_config(&self.ray_through(x, y), &self.config);
+        }
+        let samples = self.lens_samples();
+        let sum: Color = (0..samples)
+            .map(|_| world.color_at_with_config(&self.lens_ray_through(x, y, rng), &self.config))
+            .sum();
+        sum * (1.0 / samples as f64)
+    }
+
+    // False only when bounds lie wholly outside the view, so no primary ray can reach them.
+    pub fn may_see(&self, bounds: &BoundingBox) -> bool {
+        let b = bounds.transform(&self.transform);
+        let (min, max) = (b.min(), b.max());
+        // Lens rays start up to aperture off the axis and, at depth d, stray at most
+        // aperture * (1 + d / focal_distance) from the pinhole ray through the same pixel.
+        let (offset, spread) = if self.aperture > 0.0 {
+            (self.aperture, self.aperture / self.focal_distance)
+        } else {
+            (0.0, 0.0)
+        };
+        let (sx, sy) = (self.half_width + spread, self.half_height * self.pixel_aspect + spread);
+        // Camera space looks down -z, so each side of the view is a plane; with a lens the
+        // planes are widened and pushed out by the aperture.
+        let outside = min.z() > 0.0
+            || min.x() + sx * min.z() - offset > 0.0
+            || -max.x() + sx * min.z() - offset > 0.0
+            || min.y() + sy * min.z() - offset > 0.0
+            || -max.y() + sy * min.z() - offset > 0.0;
+        !outside
+    }
+
+    // Shades the center of one pixel, reporting what its primary ray hit along the way.
+    pub fn debug_pixel(&self, world: &World, px: usize, py: usize) -> (Color, DebugTrace) {
+        world.debug_color_at(&self.ray_for_pixel(px, py), &self.config)
+    }
+
     pub fn render(&self, world: &World) -> Canvas {
+        self.render_cancellable(world, &AtomicBool::new(false))
+            .expect("Render without a cancel request always completes")
+    }
+
+    // Scanlines are rendered in parallel and each checks the flag before it starts, so a
+    // cancelled render stops once the rows already in flight finish.
+    pub fn render_cancellable(&self, world: &World, cancel: &AtomicBool) -> Option<Canvas> {
+        let seed = self.render_seed();
+        let rows: Vec<Vec<Color>> = (0..self.vsize)
+            .into_par_iter()
+            .map(|y| (!cancel.load(Ordering::Relaxed)).then(|| self.render_row(world, y, seed)))
+            .collect::<Option<_>>()?;
         let mut image = Canvas::new(self.hsize, self.vsize);
-        for y in 0..self.vsize {
-            for x in 0..self.hsize {
-                let mut ray = self.ray_for_pixel(x, y);
-                let color = world.color_at(&mut ray);
+        for (y, row) in rows.into_iter().enumerate() {
+            for (x, color) in row.into_iter().enumerate() {
                 image.write_pixel(x, y, color);
             }
         }
-        image
+        Some(image)
+    }
+
+    // Renders lazily one scanline per item, top to bottom, as (row index, pixels). Rows match
+    // render exactly, seeded sampling included, so a viewer can draw each as it arrives.
+    pub fn render_iter<'a>(&'a self, world: &'a World) -> impl Iterator<Item = (usize, Vec<Color>)> + 'a {
+        let seed = self.render_seed();
+        (0..self.vsize).map(move |y| (y, self.render_row(world, y, seed)))
+    }
+
+    fn render_seed(&self) -> u64 {
+        match self.antialiasing {
+            AntialiasMode::Stochastic { seed: Some(seed), .. } => seed,
+            _ => self.seed,
+        }
+    }
+
+    // Each row draws from its own generator, so rows come out the same in any order.
+    fn render_row(&self, world: &World, y: usize, seed: u64) -> Vec<Color> {
+        let mut rng = Rng::new(seed ^ (y as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        (0..self.hsize)
+            .map(|x| self.pixel_color(world, x, y, &mut rng))
+            .collect()
+    }
+
+    pub fn render_supersampled(&self, world: &World, factor: usize) -> Canvas {
+        self.clone()
+            .with_antialiasing(AntialiasMode::Grid(factor.max(1)))
+            .render(world)
+    }
+
+    fn pixel_color(&self, world: &World, px: usize, py: usize, rng: &mut Rng) -> Color {
+        let (x, y) = (px as f64, py as f64);
+        match self.antialiasing {
+            AntialiasMode::None | AntialiasMode::Grid(0) | AntialiasMode::Grid(1) => {
+                self.sample_color(world, x + 0.5, y + 0.5, rng)
+            }
+            AntialiasMode::Grid(n) => {
+                let step = 1.0 / n as f64;
+                let sum: Color = (0..n * n)
+                    .map(|i| {
+                        let sx = x + ((i % n) as f64 + 0.5) * step;
+                        let sy = y + ((i / n) as f64 + 0.5) * step;
+                        self.sample_color(world, sx, sy, rng)
+                    })
+                    .sum();
+                sum * (step * step)
+            }
+            AntialiasMode::Stochastic { samples, .. } => {
+                let samples = samples.max(1);
+                let sum: Color = (0..samples)
+                    .map(|_| {
+                        let (sx, sy) = (x + rng.next_f64(), y + rng.next_f64());
+                        self.sample_color(world, sx, sy, rng)
+                    })
+                    .sum();
+                sum * (1.0 / samples as f64)
+            }
+            AntialiasMode::Adaptive { threshold, max_depth } => {
+                self.adaptive_sample(world, x, y, 1.0, threshold, max_depth, rng)
+            }
+        }
+    }
+
+    // Samples the corners and center of a square; if they disagree by more than the
+    // threshold the square is split into four and each quarter is sampled the same way.
+    #[allow(clippy::too_many_arguments)]
+    fn adaptive_sample(
+        &self,
+        world: &World,
+        x: f64,
+        y: f64,
+        size: f64,
+        threshold: f64,
+        depth: u8,
+        rng: &mut Rng,
+    ) -> Color {
+        let mut sample = |sx: f64, sy: f64| self.sample_color(world, sx, sy, rng);
+        let half = size / 2.0;
+        let center = sample(x + half, y + half);
+        let corners = [
+            sample(x, y),
+            sample(x + size, y),
+            sample(x, y + size),
+            sample(x + size, y + size),
+        ];
+        let differs = corners.iter().any(|c| {
+            let d = *c - center;
+            d.red().abs().max(d.green().abs()).max(d.blue().abs()) > threshold
+        });
+        if depth == 0 || !differs {
+            return (corners.into_iter().sum::<Color>() + center) * 0.2;
+        }
+        let quarters: Color = [(x, y), (x + half, y), (x, y + half), (x + half, y + half)]
+            .into_iter()
+            .map(|(qx, qy)| self.adaptive_sample(world, qx, qy, half, threshold, depth - 1, rng))
+            .sum();
+        quarters * 0.25
+    }
+
+    // Places the camera on a sphere around target and points it there. Azimuth 0 is the +z
+    // side and grows toward +x; elevation is the angle above the xz plane, kept just short of
+    // the poles where the up vector would be ambiguous. A zero radius leaves no direction to
+    // look in, so the camera is left where it was and the error returned.
+    pub fn orbit(
+        &mut self,
+        target: Point,
+        radius: f64,
+        azimuth: f64,
+        elevation: f64,
+    ) -> Result<(), CameraError> {
+        let limit = std::f64::consts::FRAC_PI_2 - 1e-6;
+        let elevation = elevation.clamp(-limit, limit);
+        let offset = Vector::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            elevation.cos() * azimuth.cos(),
+        );
+        let from = target + offset * radius;
+        let transform = try_view_transform(from, target, Vector::new(0.0, 1.0, 0.0))?;
+        self.transform_inverse = finite_inverse(&transform).ok_or(CameraError::SingularTransform)?;
+        self.transform = transform;
+        Ok(())
     }
 
     pub fn set_transform(mut self, transform: Matrix) -> Self{
@@ -64,14 +388,32 @@ impl Camera {
         self.transform_inverse = transform.inverse().unwrap();
         self
     }
+
+    // Like set_transform, but leaves a bad transform to the caller instead of panicking.
+    pub fn try_set_transform(mut self, transform: Matrix) -> Result<Self, CameraError> {
+        let inverse = finite_inverse(&transform).ok_or(CameraError::SingularTransform)?;
+        self.transform = transform;
+        self.transform_inverse = inverse;
+        Ok(self)
+    }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use crate::float::ApproxEq;
-    use crate::primitives::{Vector, Color};
+    use crate::primitives::Vector;
     use crate::rtc::transformation::view_transform;
+    use std::f64::consts::PI;
+
+    // Five units back on -z, looking at the origin: the view most render tests share.
+    pub(crate) fn camera_facing_origin(hsize: usize, vsize: usize, field_of_view: f64) -> Camera {
+        Camera::new(hsize, vsize, field_of_view, Matrix::id()).set_transform(view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::zero(),
+            Vector::new(0.0, 1.0, 0.0),
+        ))
+    }
     #[test]
     fn test_camera() {
         let c = Camera::new(160, 120, std::f64::consts::PI / 2.0, Matrix::id());
@@ -117,6 +459,45 @@ mod tests {
         assert_eq!(r.direction(), Vector::new(2.0_f64.sqrt() / 2.0, 0.0, -2.0_f64.sqrt() / 2.0));
     }
 
+    #[test]
+    fn pixel_aspect_stretches_vertical_field() {
+        let square = Camera::new(201, 101, std::f64::consts::PI / 2.0, Matrix::id());
+        let stretched =
+            Camera::new(201, 101, std::f64::consts::PI / 2.0, Matrix::id()).with_pixel_aspect(2.0);
+        assert_eq!(
+            stretched.ray_for_pixel(100, 50).direction(),
+            Vector::new(0.0, 0.0, -1.0)
+        );
+        let a = square.ray_for_pixel(0, 0).direction();
+        let b = stretched.ray_for_pixel(0, 0).direction();
+        assert!((b.y() / b.x()).approx_eq(2.0 * a.y() / a.x()));
+    }
+
+    #[test]
+    fn orbit_at_zero_azimuth_is_on_positive_z() {
+        let target = Point::new(1.0, 2.0, 3.0);
+        let mut c = Camera::new(11, 11, std::f64::consts::PI / 2.0, Matrix::id());
+        c.orbit(target, 5.0, 0.0, 0.0).unwrap();
+        let center = c.ray_for_pixel(5, 5);
+        assert_eq!(center.origin(), Point::new(1.0, 2.0, 8.0));
+        assert_eq!(center.direction(), Vector::new(0.0, 0.0, -1.0));
+        c.orbit(target, 5.0, std::f64::consts::FRAC_PI_2, std::f64::consts::FRAC_PI_4).unwrap();
+        let center = c.ray_for_pixel(5, 5);
+        assert!((target - center.origin()).magnitude().approx_eq(5.0));
+        assert_eq!(center.direction(), (target - center.origin()).normalize());
+    }
+
+    #[test]
+    fn orbit_with_zero_radius_is_an_error() {
+        let target = Point::new(1.0, 2.0, 3.0);
+        let mut c = Camera::new(11, 11, std::f64::consts::PI / 2.0, Matrix::id());
+        c.orbit(target, 5.0, 0.0, 0.0).unwrap();
+        let before = c.transform;
+        assert_eq!(c.orbit(target, 0.0, 0.0, 0.0), Err(CameraError::View(ViewError::NoDirection)));
+        assert_eq!(c.orbit(target, f64::NAN, 0.0, 0.0), Err(CameraError::SingularTransform));
+        assert_eq!(c.transform, before);
+    }
+
     #[test]
     fn render_world_with_camera() {
         let w = World::default();
@@ -128,4 +509,311 @@ mod tests {
         let image = c.render(&w);
         assert_eq!(image.pixel_at(5, 5), Color::new(0.38066, 0.47583, 0.2855));
     }
+
+    #[test]
+    fn render_shows_sphere_reflected_in_floor() {
+        use crate::rtc::{light::PointLight, material::Material, object::Object};
+        // a half-silvered black floor and a red sphere that lights itself
+        let mirror = Material::new()
+            .with_color(Color::black())
+            .with_ambient(0.0)
+            .with_diffuse(0.0)
+            .with_specular(0.0)
+            .with_reflective(0.5);
+        let red = Material::new()
+            .with_color(Color::new(1.0, 0.0, 0.0))
+            .with_ambient(1.0)
+            .with_diffuse(0.0)
+            .with_specular(0.0);
+        let floor = Object::new_plane().set_material(&mirror);
+        let ball = Object::new_sphere()
+            .set_transform(Matrix::id().translate(0.0, 1.0, 3.0))
+            .set_material(&red);
+        let w = World::new()
+            .with_objects(vec![floor, ball])
+            .with_lights(vec![Box::new(PointLight::new(Color::white(), Point::new(0.0, 10.0, -10.0)))]);
+        // the center ray meets the floor at the origin and bounces straight into the sphere
+        let c = Camera::new(11, 11, std::f64::consts::PI / 3.0, Matrix::id()).set_transform(view_transform(
+            Point::new(0.0, 1.0, -3.0),
+            Point::zero(),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let image = c.render(&w);
+        assert_eq!(image.pixel_at(5, 5), Color::new(0.5, 0.0, 0.0));
+        assert_eq!(c.render(&w.with_depth(0)).pixel_at(5, 5), Color::black());
+    }
+
+    #[test]
+    fn lens_samples_only_apply_with_depth_of_field() {
+        let c = Camera::new(11, 11, std::f64::consts::PI / 2.0, Matrix::id()).with_lens_samples(8);
+        assert_eq!(c.lens_samples(), 1);
+        let c = c.with_depth_of_field(0.2, 4.0);
+        assert_eq!(c.lens_samples(), 8);
+        assert_eq!(c.with_lens_samples(0).lens_samples(), 1);
+    }
+
+    #[test]
+    fn lens_rays_meet_at_focal_plane() {
+        let c = Camera::new(11, 11, std::f64::consts::PI / 2.0, Matrix::id()).with_depth_of_field(0.5, 4.0);
+        let mut rng = Rng::new(3);
+        let pinhole = c.ray_through(3.5, 7.5);
+        let focus = pinhole.position(4.0 / -pinhole.direction().z());
+        for _ in 0..8 {
+            let r = c.lens_ray_through(3.5, 7.5, &mut rng);
+            assert!(r.origin().z().approx_eq(0.0));
+            let t = (focus.z() - r.origin().z()) / r.direction().z();
+            assert_eq!(r.position(t), focus);
+        }
+    }
+
+    #[test]
+    fn lens_keeps_objects_only_lens_rays_reach() {
+        use crate::rtc::object::Object;
+        // just right of the pinhole frustum, which is 1 wide at depth 1
+        let ball = Object::new_sphere()
+            .set_transform(Matrix::id().scale(0.15, 0.15, 0.15).translate(1.3, 0.0, -1.0));
+        let pinhole = Camera::new(11, 11, std::f64::consts::PI / 2.0, Matrix::id());
+        let lens = pinhole.clone().with_depth_of_field(0.5, 5.0);
+        assert!(!pinhole.may_see(ball.world_bounds()));
+        assert!(lens.may_see(ball.world_bounds()));
+        // from the lens rim toward the focal plane at the right edge of the view
+        let rim = Point::new(0.5, 0.0, 0.0);
+        let r = Ray::new(rim, (Point::new(5.0, 0.0, -5.0) - rim).normalize());
+        assert!(ball.any_hit(&r, f64::INFINITY, &RenderConfig::default()));
+        let w = World::new().with_objects(vec![ball]).cull_to_frustum(&lens);
+        let (_, trace) = w.debug_color_at(&r, &RenderConfig::default());
+        assert_eq!(trace.hit_object, Some(0));
+    }
+
+    #[test]
+    fn in_focus_surface_renders_like_pinhole() {
+        let w = World::default();
+        let c = camera_facing_origin(11, 11, PI / 2.0);
+        let sharp = c.render(&w).pixel_at(5, 5);
+        let focused = c.with_depth_of_field(0.1, 4.0).with_lens_samples(16).render(&w).pixel_at(5, 5);
+        let d = sharp - focused;
+        assert!(d.red().abs().max(d.green().abs()).max(d.blue().abs()) < 0.02);
+    }
+
+    #[test]
+    fn debug_pixel_reports_hit_at_center_of_default_world() {
+        let w = World::default();
+        let c = camera_facing_origin(11, 11, PI / 2.0);
+        let (color, trace) = c.debug_pixel(&w, 5, 5);
+        assert_eq!(color, Color::new(0.38066, 0.47583, 0.2855));
+        assert_eq!(trace.hit_object, Some(0));
+        assert!(!trace.in_shadow);
+        assert_eq!(trace.reflection, Color::black());
+        assert_eq!(trace.refraction, Color::black());
+        assert_eq!(trace.depth_reached, 0);
+    }
+
+    #[test]
+    fn supersample_factor_one_is_plain_render() {
+        let w = World::default();
+        let c = camera_facing_origin(11, 11, PI / 2.0);
+        let plain = c.render(&w);
+        let supersampled = c.render_supersampled(&w, 1);
+        for y in 0..11 {
+            for x in 0..11 {
+                assert_eq!(supersampled.pixel_at(x, y), plain.pixel_at(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn supersample_blends_sphere_edge() {
+        let w = World::default();
+        let c = camera_facing_origin(21, 21, PI / 6.0);
+        let plain = c.render(&w);
+        let supersampled = c.render_supersampled(&w, 2);
+        assert_eq!(supersampled.width(), 21);
+        assert_eq!(supersampled.length(), 21);
+        assert_eq!(supersampled.pixel_at(10, 10), plain.pixel_at(10, 10));
+        let edge = (0..21).find(|&x| plain.pixel_at(x, 10) != Color::black()).unwrap();
+        let blended = supersampled.pixel_at(edge - 1, 10);
+        assert_eq!(plain.pixel_at(edge - 1, 10), Color::black());
+        assert_ne!(blended, Color::black());
+        assert!(blended.green() < plain.pixel_at(edge, 10).green());
+    }
+
+    #[test]
+    fn render_cancelled_before_start_returns_none() {
+        let w = World::default();
+        let c = Camera::new(1000, 1000, std::f64::consts::PI / 2.0, Matrix::id());
+        let cancel = AtomicBool::new(true);
+        assert!(c.render_cancellable(&w, &cancel).is_none());
+    }
+
+    #[test]
+    fn render_not_cancelled_matches_render() {
+        let w = World::default();
+        let c = camera_facing_origin(11, 11, PI / 2.0);
+        let cancel = AtomicBool::new(false);
+        let image = c.render_cancellable(&w, &cancel).unwrap();
+        assert_eq!(image.pixel_at(5, 5), c.render(&w).pixel_at(5, 5));
+    }
+
+    #[test]
+    fn stochastic_rows_render_the_same_in_any_order() {
+        let w = World::default();
+        let c = Camera::new(11, 11, std::f64::consts::PI / 2.0, Matrix::id())
+            .with_antialiasing(AntialiasMode::Stochastic { samples: 2, seed: Some(5) });
+        let image = c.render_cancellable(&w, &AtomicBool::new(false)).unwrap();
+        let seed = c.render_seed();
+        for y in (0..11).rev() {
+            for (x, color) in c.render_row(&w, y, seed).into_iter().enumerate() {
+                assert_eq!(image.pixel_at(x, y), color);
+            }
+        }
+    }
+
+    #[test]
+    fn antialias_none_matches_single_center_ray() {
+        let w = World::default();
+        let c = camera_facing_origin(11, 11, PI / 2.0)
+            .with_antialiasing(AntialiasMode::None);
+        let image = c.render(&w);
+        for y in 0..11 {
+            for x in 0..11 {
+                let expected = w.color_at(&mut c.ray_for_pixel(x, y));
+                assert!(image.pixel_at(x, y).approx_eq_epsilon(&expected, 0.0));
+            }
+        }
+    }
+
+    #[test]
+    fn every_antialias_mode_renders() {
+        let w = World::default();
+        let modes = [
+            AntialiasMode::None,
+            AntialiasMode::Grid(3),
+            AntialiasMode::Stochastic { samples: 4, seed: Some(7) },
+            AntialiasMode::Adaptive { threshold: 0.1, max_depth: 2 },
+        ];
+        let mut center = None;
+        for mode in modes {
+            let c = camera_facing_origin(21, 21, PI / 6.0)
+                .with_antialiasing(mode)
+                .with_seed(7);
+            let image = c.render(&w);
+            assert_eq!(image.width(), 21);
+            assert_eq!(image.length(), 21);
+            // the middle of the sphere is smooth enough that every mode roughly agrees there
+            let middle = image.pixel_at(10, 10);
+            let expected = *center.get_or_insert(middle);
+            assert!(middle.approx_eq_epsilon(&expected, 0.01));
+            assert_eq!(image.pixel_at(0, 0), Color::black());
+        }
+    }
+
+    #[test]
+    fn render_iter_rows_match_render() {
+        let w = World::default();
+        let c = camera_facing_origin(15, 9, PI / 3.0)
+            .with_antialiasing(AntialiasMode::Stochastic { samples: 2, seed: None })
+            .with_seed(3);
+        let mut streamed = Canvas::new(15, 9);
+        let mut next_row = 0;
+        for (y, row) in c.render_iter(&w) {
+            assert_eq!((y, row.len()), (next_row, 15));
+            for (x, color) in row.into_iter().enumerate() {
+                streamed.write_pixel(x, y, color);
+            }
+            next_row += 1;
+        }
+        assert_eq!(next_row, 9);
+        let image = c.render(&w);
+        for y in 0..9 {
+            for x in 0..15 {
+                assert_eq!(streamed.pixel_at(x, y), image.pixel_at(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn seed_makes_stochastic_renders_reproducible() {
+        let w = World::default();
+        let render = |seed| {
+            camera_facing_origin(21, 21, PI / 6.0)
+                .with_antialiasing(AntialiasMode::Stochastic { samples: 4, seed: None })
+                .with_seed(seed)
+                .render(&w)
+                .to_ppm()
+        };
+        assert_eq!(render(1), render(1));
+        assert_ne!(render(1), render(2));
+    }
+
+    #[test]
+    fn stochastic_mode_seed_overrides_camera_seed() {
+        let w = World::default();
+        let render = |mode_seed, camera_seed| {
+            camera_facing_origin(21, 21, PI / 6.0)
+                .with_antialiasing(AntialiasMode::Stochastic { samples: 4, seed: mode_seed })
+                .with_seed(camera_seed)
+                .render(&w)
+                .to_ppm()
+        };
+        assert_eq!(render(Some(3), 1), render(Some(3), 2));
+        assert_eq!(render(Some(3), 1), render(None, 3));
+    }
+
+    #[test]
+    fn shadow_bias_moves_the_shadow_edge() {
+        use crate::rtc::{light::PointLight, object::Object};
+        let floor = Object::new_plane();
+        let ball = Object::new_sphere().set_transform(Matrix::id().translate(0.0, 1.0, 0.0));
+        let w = World::new()
+            .with_objects(vec![floor, ball])
+            .with_lights(vec![Box::new(PointLight::new(
+                Color::white(),
+                Point::new(-5.0, 5.0, -5.0),
+            ))]);
+        let c = Camera::new(21, 21, std::f64::consts::PI / 3.0, Matrix::id()).set_transform(
+            view_transform(
+                Point::new(0.0, 4.0, -6.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ),
+        );
+        let tight = c.clone().with_config(RenderConfig::new()).render(&w);
+        assert_eq!(tight.to_ppm(), c.render(&w).to_ppm());
+        let loose = c
+            .with_config(RenderConfig::new().with_shadow_bias(0.5))
+            .render(&w);
+        let changed = (0..21)
+            .flat_map(|y| (0..21).map(move |x| (x, y)))
+            .filter(|&(x, y)| tight.pixel_at(x, y) != loose.pixel_at(x, y))
+            .count();
+        assert!(changed > 0);
+    }
+
+    #[test]
+    fn degenerate_view_is_an_error() {
+        let p = Point::new(1.0, 2.0, 3.0);
+        let up = Vector::new(0.0, 1.0, 0.0);
+        let fov = std::f64::consts::PI / 2.0;
+        let view = |to: Point| -> Result<Camera, CameraError> {
+            let transform = crate::rtc::transformation::try_view_transform(p, to, up)?;
+            Camera::try_new(10, 10, fov, transform)
+        };
+        assert_eq!(view(p).err(), Some(CameraError::View(ViewError::NoDirection)));
+        assert!(view(Point::zero()).is_ok());
+        assert_eq!(
+            Camera::try_new(10, 10, fov, Matrix::id().scale(0.0, 1.0, 1.0)).err(),
+            Some(CameraError::SingularTransform)
+        );
+    }
+
+    #[test]
+    fn try_set_transform_rejects_singular_transform() {
+        let c = Camera::new(10, 10, std::f64::consts::PI / 2.0, Matrix::id());
+        assert_eq!(
+            c.clone().try_set_transform(Matrix::id().scale(0.0, 1.0, 1.0)).err(),
+            Some(CameraError::SingularTransform)
+        );
+        let moved = c.try_set_transform(Matrix::id().translate(0.0, 0.0, -5.0)).unwrap();
+        assert_eq!(moved.transform_inverse, Matrix::id().translate(0.0, 0.0, 5.0));
+    }
 }
@@ -0,0 +1,34 @@
+use crate::float::epsilon::EPSILON;
+
+// Tolerances for one render; the defaults are the crate-wide constants. Scenes built at very
+// large or very small scales can loosen or tighten them without touching the globals.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderConfig {
+    pub shadow_bias: f64,      // how far over_point and under_point sit off the surface
+    pub parallel_epsilon: f64, // below this a ray counts as parallel to a plane, disk, cylinder or cone
+}
+
+impl RenderConfig {
+    pub fn new() -> Self {
+        RenderConfig::default()
+    }
+
+    pub fn with_shadow_bias(mut self, bias: f64) -> Self {
+        self.shadow_bias = bias;
+        self
+    }
+
+    pub fn with_parallel_epsilon(mut self, epsilon: f64) -> Self {
+        self.parallel_epsilon = epsilon;
+        self
+    }
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        RenderConfig {
+            shadow_bias: EPSILON,
+            parallel_epsilon: EPSILON,
+        }
+    }
+}
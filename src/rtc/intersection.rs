@@ -1,5 +1,5 @@
 use crate::{
-    float::{epsilon::EPSILON, ApproxEq},
+    float::epsilon::EPSILON,
     primitives::{Point, Vector},
     rtc::{object::Object, ray::Ray},
 };
@@ -10,16 +10,31 @@ use std::{cmp::Ord, cmp::Ordering, cmp::PartialOrd, ops::Index};
 pub struct Intersection<'a> {
     t: f64,
     object: &'a Object,
+    uv: Option<(f64, f64)>, // surface parameters at the hit, for shapes that report them
 }
 
 impl<'a> Intersection<'a> {
     pub fn new(t: f64, object: &'a Object) -> Self {
-        Intersection { t, object }
+        Intersection { t, object, uv: None }
     }
+
+    pub fn with_uv(mut self, u: f64, v: f64) -> Self {
+        self.uv = Some((u, v));
+        self
+    }
+
     pub fn t(&self) -> f64 {
         self.t
     }
 
+    pub fn u(&self) -> Option<f64> {
+        self.uv.map(|(u, _)| u)
+    }
+
+    pub fn v(&self) -> Option<f64> {
+        self.uv.map(|(_, v)| v)
+    }
+
     pub fn object(&self) -> &'a Object {
         self.object
     }
@@ -33,17 +48,20 @@ impl PartialOrd for Intersection<'_> {
 
 impl<'a> Ord for Intersection<'a> {
     fn cmp(&self, other: &Self) -> Ordering {
-        if self.t.is_nan() {
-            Ordering::Greater
-        } else if other.t.is_nan() {
-            return Ordering::Less;
-        } else if self.t < other.t {
-            Ordering::Less
-        } else if self.t > other.t {
-            Ordering::Greater
-        } else {
-            Ordering::Equal
-        }
+        compare_t(self.t, other.t)
+    }
+}
+
+// NaN sorts last so it never becomes the hit.
+fn compare_t(a: f64, b: f64) -> Ordering {
+    if a.is_nan() {
+        Ordering::Greater
+    } else if b.is_nan() || a < b {
+        Ordering::Less
+    } else if a > b {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
     }
 }
 
@@ -76,10 +94,34 @@ impl<'a> Intersections<'a> {
         self.intersections.push(Intersection::new(t, object))
     }
 
+    pub fn push_with_uv(&mut self, object: &'a Object, t: f64, u: f64, v: f64) {
+        self.intersections.push(Intersection::new(t, object).with_uv(u, v))
+    }
+
     pub fn extend(&mut self, other: Self) {
         self.intersections.extend(other.intersections);
     }
 
+    // Merges two lists that are already sorted, keeping the result sorted without a full sort.
+    pub fn extend_sorted(&mut self, other: Self) {
+        let mine = std::mem::take(&mut self.intersections);
+        let mut merged = Vec::with_capacity(mine.len() + other.count());
+        let mut mine = mine.into_iter().peekable();
+        let mut theirs = other.intersections.into_iter().peekable();
+        loop {
+            let next = match (mine.peek(), theirs.peek()) {
+                (Some(a), Some(b)) if b < a => theirs.next(),
+                (Some(_), _) => mine.next(),
+                (None, _) => theirs.next(),
+            };
+            match next {
+                Some(i) => merged.push(i),
+                None => break,
+            }
+        }
+        self.intersections = merged;
+    }
+
     pub fn count(&self) -> usize {
         self.intersections.len()
     }
@@ -88,18 +130,40 @@ impl<'a> Intersections<'a> {
         self.intersections.iter()
     }
 
-    pub fn into_iter(self) -> std::vec::IntoIter<Intersection<'a>> {
-        self.intersections.into_iter()
-    }
-
     pub fn hit(&self) -> Option<&Intersection<'a>> {
         self.iter().find(|i| i.t() >= 0.0)
     }
 
+    pub fn hit_filtered(&self, pred: impl Fn(&Intersection) -> bool) -> Option<&Intersection<'a>> {
+        self.iter().find(|i| i.t() >= 0.0 && pred(i))
+    }
+
+    // Stable, so intersections with equal t keep the order they were added in.
     pub fn sort(mut self) -> Intersections<'a> {
-        self.intersections.sort_unstable();
+        self.intersections.sort();
         self
     }
+
+    // Sorts and returns the index of the hit; once sorted it is the first non-negative t,
+    // found by binary search instead of another walk over the list.
+    pub fn sort_with_hit(self) -> (Intersections<'a>, Option<usize>) {
+        let sorted = self.sort();
+        let index = sorted.intersections.partition_point(|i| i.t() < 0.0);
+        let hit = sorted
+            .intersections
+            .get(index)
+            .filter(|i| i.t() >= 0.0)
+            .map(|_| index);
+        (sorted, hit)
+    }
+}
+
+impl<'a> IntoIterator for Intersections<'a> {
+    type Item = Intersection<'a>;
+    type IntoIter = std::vec::IntoIter<Intersection<'a>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.intersections.into_iter()
+    }
 }
 
 impl<'a> Index<usize> for Intersections<'a> {
@@ -109,6 +173,98 @@ impl<'a> Index<usize> for Intersections<'a> {
     }
 }
 
+// Like Intersection, but names the object by its index into a slice of objects instead of
+// borrowing it, so lists can outlive the borrow and be resolved later.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct IndexedIntersection {
+    t: f64,
+    object_index: usize,
+    uv: Option<(f64, f64)>,
+}
+
+impl IndexedIntersection {
+    pub fn new(t: f64, object_index: usize) -> Self {
+        IndexedIntersection { t, object_index, uv: None }
+    }
+
+    pub fn from_intersection(intersection: &Intersection, object_index: usize) -> Self {
+        IndexedIntersection {
+            t: intersection.t,
+            object_index,
+            uv: intersection.uv,
+        }
+    }
+
+    pub fn t(&self) -> f64 {
+        self.t
+    }
+
+    pub fn object_index(&self) -> usize {
+        self.object_index
+    }
+
+    pub fn object<'a>(&self, objects: &'a [Object]) -> &'a Object {
+        &objects[self.object_index]
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct IndexedIntersections {
+    intersections: Vec<IndexedIntersection>,
+}
+
+impl IndexedIntersections {
+    pub fn new() -> Self {
+        IndexedIntersections::default()
+    }
+
+    pub fn push_intersection(&mut self, object_index: usize, intersection: &Intersection) {
+        self.intersections
+            .push(IndexedIntersection::from_intersection(intersection, object_index))
+    }
+
+    pub fn push(&mut self, object_index: usize, t: f64) {
+        self.intersections.push(IndexedIntersection::new(t, object_index))
+    }
+
+    pub fn count(&self) -> usize {
+        self.intersections.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, IndexedIntersection> {
+        self.intersections.iter()
+    }
+
+    pub fn hit(&self) -> Option<&IndexedIntersection> {
+        self.iter().find(|i| i.t() >= 0.0)
+    }
+
+    pub fn sort(mut self) -> IndexedIntersections {
+        self.intersections.sort_by(|a, b| compare_t(a.t, b.t));
+        self
+    }
+
+    // Borrows the objects back in, in the same order.
+    pub fn resolve<'a>(&self, objects: &'a [Object]) -> Intersections<'a> {
+        Intersections::new().with_intersections(
+            self.iter()
+                .map(|i| Intersection {
+                    t: i.t,
+                    object: i.object(objects),
+                    uv: i.uv,
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Index<usize> for IndexedIntersections {
+    type Output = IndexedIntersection;
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.intersections[index]
+    }
+}
+
 pub struct IntersectionState<'a> {
     t: f64,
     object: &'a Object,
@@ -122,50 +278,33 @@ pub struct IntersectionState<'a> {
     n2: f64,
     under_point: Point,
     is_entering: bool,
+    reflectance: f64,
+    refractive_indices: Vec<f64>, // the ray's index stack once past this surface
 }
-#[derive(Debug)]
-struct RefractionState {
-    n1: f64,
-    n2: f64,
-    is_entering: bool,
-}
-
-fn calculate_refraction_state(ray: &Ray, intersection: &Intersection) -> RefractionState {
-    // Different algorithm for calculating refraction index
-    // Store the refraction indices encountered by the ray so far inside the ray in a stack
-    // When a ray intersects an object, it checks if it is entering or exiting the objects
-    // If it is entering, it pushes the object's refraction index to the stack
-    // If it is exiting, it pops the object's refraction index from the stack
-    let current_index = intersection.object().material().refractive_index();
-    let objects = ray.get_indices();
-    let is_entering = (*objects)
-        .iter()
-        .find(|o| (*o).approx_eq(current_index))
-        .is_none();
-    let previous_refraction_index: f64 = *objects
-        .last()
-        .expect("Never should be empty - outside world is always 1.0");
-    if is_entering {
-        return RefractionState {
-            n1: previous_refraction_index,
-            n2: current_index,
-            is_entering: true,
-        };
-    }
-    let prev = objects
-        .iter()
-        .rev()
-        .find(|o| !(*o).approx_eq(current_index));
-    let new_refraction_index = prev.unwrap_or(&previous_refraction_index);
 
-    RefractionState {
-        n1: previous_refraction_index,
-        n2: *new_refraction_index,
-        is_entering: false,
-    }
+// The ray keeps a stack of the indices of the objects it is inside, outermost first. Whether it
+// enters or exits comes from the surface normal, not from looking the index up in the stack, so
+// nested objects that share an index (or a hollow shell of air inside glass) still push and pop
+// exactly once per surface. n1 is the top of the stack before the hit and n2 the top after it.
+fn update_refraction_stack(ray: &mut Ray, refractive_index: f64, is_entering: bool) -> (f64, f64) {
+    let top = |ray: &Ray| ray.get_indices().last().copied().unwrap_or(1.0);
+    let n1 = if is_entering {
+        let n1 = top(ray);
+        ray.add_index(refractive_index);
+        n1
+    } else if ray.get_indices().contains(&refractive_index) {
+        let n1 = top(ray);
+        ray.remove_index(refractive_index);
+        n1
+    } else {
+        // exiting something the ray was never recorded entering, so it started inside it
+        refractive_index
+    };
+    (n1, top(ray))
 }
 
 impl<'a> IntersectionState<'a> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         t: f64,
         object: &'a Object,
@@ -180,7 +319,7 @@ impl<'a> IntersectionState<'a> {
         n2: f64,
         is_entering: bool,
     ) -> Self {
-        IntersectionState {
+        let mut state = IntersectionState {
             t,
             object,
             eyev,
@@ -193,20 +332,46 @@ impl<'a> IntersectionState<'a> {
             n2,
             under_point,
             is_entering,
-        }
+            reflectance: 0.0,
+            refractive_indices: vec![n2],
+        };
+        state.reflectance = state.schlick();
+        state
     }
 
     pub fn prepare_computations(
         intersection: &'a Intersection,
         ray: &mut Ray,
+    ) -> IntersectionState<'a> {
+        IntersectionState::prepare_computations_with_bias(intersection, ray, EPSILON)
+    }
+
+    // shadow_bias is the offset of over_point and under_point for an unscaled object.
+    pub fn prepare_computations_with_bias(
+        intersection: &'a Intersection,
+        ray: &mut Ray,
+        shadow_bias: f64,
+    ) -> IntersectionState<'a> {
+        IntersectionState::prepare(intersection, ray, shadow_bias, true)
+    }
+
+    // For scenes with nothing transparent: the ray's index stack is left alone and
+    // n1 = n2 = 1.0, since no ray is ever refracted.
+    pub fn prepare_computations_opaque(
+        intersection: &'a Intersection,
+        ray: &mut Ray,
+        shadow_bias: f64,
+    ) -> IntersectionState<'a> {
+        IntersectionState::prepare(intersection, ray, shadow_bias, false)
+    }
+
+    fn prepare(
+        intersection: &'a Intersection,
+        ray: &mut Ray,
+        shadow_bias: f64,
+        track_indices: bool,
     ) -> IntersectionState<'a> {
         let t = intersection.t();
-        let state = calculate_refraction_state(ray, intersection);
-        if state.is_entering {
-            ray.add_index(intersection.object().material().refractive_index());
-        } else {
-            ray.remove_index(intersection.object().material().refractive_index());
-        }
         let object = intersection.object();
         let point = ray.position(t);
         let eyev = -ray.direction();
@@ -218,11 +383,18 @@ impl<'a> IntersectionState<'a> {
                 (normalv, false)
             }
         };
-        let over_point = point + normalv * EPSILON;
-        let under_point = point - normalv * EPSILON;
+        // scaled up objects sit at larger coordinates and carry more rounding error
+        let bias = shadow_bias * object.transform_scale().max(1.0);
+        let over_point = point + normalv * bias;
+        let under_point = point - normalv * bias;
         let reflectv = ray.direction().reflect(&normalv);
+        let (n1, n2) = if track_indices {
+            update_refraction_stack(ray, object.material().refractive_index(), !inside)
+        } else {
+            (1.0, 1.0)
+        };
 
-        IntersectionState::new(
+        let mut state = IntersectionState::new(
             t,
             object,
             eyev,
@@ -232,10 +404,14 @@ impl<'a> IntersectionState<'a> {
             over_point,
             under_point,
             reflectv,
-            state.n1,
-            state.n2,
-            state.is_entering,
-        )
+            n1,
+            n2,
+            !inside,
+        );
+        if track_indices {
+            state.refractive_indices = ray.get_indices().clone();
+        }
+        state
     }
 
     pub fn schlick(&self) -> f64 {
@@ -294,9 +470,21 @@ impl<'a> IntersectionState<'a> {
         self.under_point
     }
 
+    pub fn inside(&self) -> bool {
+        self.inside
+    }
+
     pub fn is_entering(&self) -> bool {
         self.is_entering
     }
+
+    pub fn reflectance(&self) -> f64 {
+        self.reflectance
+    }
+
+    pub fn refractive_indices(&self) -> &[f64] {
+        &self.refractive_indices
+    }
 }
 
 #[cfg(test)]
@@ -308,6 +496,21 @@ mod tests {
         primitives::{Matrix, Tuple},
         rtc::{intersection::Intersection, material::Material},
     };
+    #[test]
+    fn extend_sorted_merges_in_order() {
+        let s = Object::new_sphere();
+        let mut a = Intersections::new().with_intersections(
+            [-1.0, 2.0, 3.0, 7.0].iter().map(|t| Intersection::new(*t, &s)).collect(),
+        );
+        let b = Intersections::new().with_intersections(
+            [0.5, 2.0, 9.0].iter().map(|t| Intersection::new(*t, &s)).collect(),
+        );
+        a.extend_sorted(b);
+        assert_eq!(a.count(), 7);
+        let ts: Vec<f64> = a.iter().map(|i| i.t()).collect();
+        assert_eq!(ts, vec![-1.0, 0.5, 2.0, 2.0, 3.0, 7.0, 9.0]);
+    }
+
     #[test]
     fn hit_when_all_intersections_have_positive_t() {
         let s = Object::new_sphere();
@@ -348,6 +551,62 @@ mod tests {
         assert_eq!(xs.hit(), Some(&i4));
     }
 
+    #[test]
+    fn sort_with_hit_finds_lowest_nonnegative_index() {
+        let s = Object::new_sphere();
+        let xs = Intersections::new()
+            .with_intersections(vec![
+                Intersection::new(5.0, &s),
+                Intersection::new(-3.0, &s),
+                Intersection::new(f64::NAN, &s),
+                Intersection::new(2.0, &s),
+            ]);
+        let (xs, hit) = xs.sort_with_hit();
+        assert_eq!(hit, Some(1));
+        assert_eq!(xs[1].t(), 2.0);
+        let (_, none) = Intersections::new()
+            .with_intersections(vec![Intersection::new(-1.0, &s)])
+            .sort_with_hit();
+        assert_eq!(none, None);
+    }
+
+    #[test]
+    fn hit_filtered_skips_rejected_object() {
+        let front = Object::new_sphere();
+        let back = Object::new_sphere().set_transform(Matrix::id().translate(0.0, 0.0, 5.0));
+        let xs = Intersections::new()
+            .with_intersections(vec![
+                Intersection::new(-1.0, &back),
+                Intersection::new(4.0, &front),
+                Intersection::new(6.0, &front),
+                Intersection::new(9.0, &back),
+                Intersection::new(11.0, &back),
+            ])
+            .sort();
+        let hit = xs.hit_filtered(|i| i.object() != &front).unwrap();
+        assert_eq!(hit.t(), 9.0);
+        assert_eq!(hit.object(), &back);
+        assert_eq!(xs.hit_filtered(|_| true), xs.hit());
+        assert_eq!(xs.hit_filtered(|_| false), None);
+    }
+
+    #[test]
+    fn sort_keeps_insertion_order_for_equal_t() {
+        let a = Object::new_sphere();
+        let b = Object::new_plane();
+        let c = Object::new_cube();
+        let xs = Intersections::new()
+            .with_intersections(vec![
+                Intersection::new(2.0, &c),
+                Intersection::new(1.0, &a),
+                Intersection::new(1.0, &b),
+                Intersection::new(1.0, &c),
+            ])
+            .sort();
+        let order: Vec<&Object> = xs.iter().map(|i| i.object()).collect();
+        assert_eq!(order, vec![&a, &b, &c, &c]);
+    }
+
     #[test]
     fn precomputing_state_of_intersection() {
         let mut r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
@@ -367,7 +626,7 @@ mod tests {
         let shape = Object::new_sphere();
         let i = Intersection::new(4.0, &shape);
         let comps = IntersectionState::prepare_computations(&i, &mut r);
-        assert_eq!(comps.inside, false);
+        assert!(!comps.inside());
     }
 
     #[test]
@@ -378,10 +637,21 @@ mod tests {
         let comps = IntersectionState::prepare_computations(&i, &mut r);
         assert_eq!(comps.point(), Point::new(0.0, 0.0, 1.0));
         assert_eq!(comps.eyev(), Vector::new(0.0, 0.0, -1.0));
-        assert_eq!(comps.inside, true);
+        assert!(comps.inside());
         assert_eq!(comps.normalv(), Vector::new(0.0, 0.0, -1.0));
     }
 
+    #[test]
+    fn ray_from_inside_transformed_sphere_is_inside() {
+        let mut r = Ray::new(Point::new(1.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+        let shape = Object::new_sphere()
+            .set_transform(Matrix::id().scale(2.0, 2.0, 2.0).translate(1.0, 0.0, 0.0));
+        let xs = shape.intersect(&r);
+        let comps = IntersectionState::prepare_computations(xs.hit().unwrap(), &mut r);
+        assert!(comps.inside());
+        assert_eq!(comps.point(), Point::new(3.0, 0.0, 0.0));
+    }
+
     #[test]
     fn precompute_reflection_vector() {
         let shape = Object::new_plane();
@@ -400,13 +670,13 @@ mod tests {
     #[test]
     fn check_refractive_indices() {
         let a = Object::new_glass_sphere()
-            .set_transform(&Matrix::id().scale(2.0, 2.0, 2.0))
+            .set_transform(Matrix::id().scale(2.0, 2.0, 2.0))
             .set_material(&Material::new().with_refractive_index(1.5));
         let b = Object::new_glass_sphere()
-            .set_transform(&Matrix::id().translate(0.0, 0.0, -0.25))
+            .set_transform(Matrix::id().translate(0.0, 0.0, -0.25))
             .set_material(&Material::new().with_refractive_index(2.0));
         let c = Object::new_glass_sphere()
-            .set_transform(&Matrix::id().translate(0.0, 0.0, 0.25))
+            .set_transform(Matrix::id().translate(0.0, 0.0, 0.25))
             .set_material(&Material::new().with_refractive_index(2.5));
         let mut r = Ray::new(Point::new(0.0, 0.0, -4.0), Vector::new(0.0, 0.0, 1.0));
         let xs = Intersections::new().with_intersections(vec![
@@ -432,11 +702,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn thin_shell_refractive_indices() {
+        let shell = Object::new_glass_sphere();
+        let hollow = Object::new_glass_sphere()
+            .set_transform(Matrix::id().scale(0.9, 0.9, 0.9))
+            .set_material(&Material::new().with_transparency(1.0).with_refractive_index(1.0));
+        let mut r = Ray::new(Point::new(0.0, 0.0, -4.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = Intersections::new().with_intersections(vec![
+            Intersection::new(3.0, &shell),
+            Intersection::new(3.1, &hollow),
+            Intersection::new(4.9, &hollow),
+            Intersection::new(5.0, &shell),
+        ]);
+        let indices = [(1.0, 1.5), (1.5, 1.0), (1.0, 1.5), (1.5, 1.0)];
+        for (i, (n1, n2)) in indices.iter().enumerate() {
+            let comps = IntersectionState::prepare_computations(&xs[i], &mut r);
+            assert!(comps.n1.approx_eq(*n1) && comps.n2.approx_eq(*n2), "hit {i}");
+        }
+        assert_eq!(r.get_indices(), &vec![1.0]);
+    }
+
     #[test]
     fn under_point_offset_below_surface() {
         let mut r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let shape =
-            Object::new_glass_sphere().set_transform(&Matrix::id().translate(0.0, 0.0, 1.0));
+            Object::new_glass_sphere().set_transform(Matrix::id().translate(0.0, 0.0, 1.0));
         let i = Intersection::new(5.0, &shape);
         let xs = Intersections::new().with_intersections(vec![i]);
         let comps = IntersectionState::prepare_computations(&xs[0], &mut r);
@@ -471,6 +762,19 @@ mod tests {
         assert!(reflectance.approx_eq(0.04));
     }
 
+    #[test]
+    fn stored_reflectance_matches_schlick() {
+        let shape = Object::new_glass_sphere();
+        let mut r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+        let xs = Intersections::new().with_intersections(vec![
+            Intersection::new(-1.0, &shape),
+            Intersection::new(1.0, &shape),
+        ]);
+        let comps = IntersectionState::prepare_computations(&xs[1], &mut r);
+        assert!(comps.reflectance().approx_eq(comps.schlick()));
+        assert!(comps.reflectance().approx_eq(0.04));
+    }
+
     #[test]
     fn schlick_with_small_angle_and_n2_greater_than_n1() {
         let shape = Object::new_glass_sphere();
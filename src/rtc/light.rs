@@ -1,9 +1,50 @@
-use crate::primitives::{Color, Point};
+use crate::primitives::{Color, Point, Vector};
+use std::fmt::Debug;
 
-#[derive(PartialEq, Debug)]
+pub trait Light: Debug + Send + Sync {
+    // Direction from point to the light, distance to it and the intensity arriving at point.
+    fn sample_toward(&self, point: &Point) -> (Vector, f64, Color);
+
+    // Points spread over the light that shadow rays aim at; lights without an area have none.
+    fn surface_points(&self) -> Vec<Point> {
+        Vec::new()
+    }
+
+    // Lights that can be written to a world snapshot; the rest make saving fail.
+    #[cfg(feature = "snapshot")]
+    fn snapshot(&self) -> Option<LightSnapshot> {
+        None
+    }
+}
+
+// Every built-in light kind, as stored in a world snapshot.
+#[cfg(feature = "snapshot")]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum LightSnapshot {
+    Point(PointLight),
+    Directional(DirectionalLight),
+    Spot(SpotLight),
+    Area(AreaLight),
+}
+
+#[cfg(feature = "snapshot")]
+impl LightSnapshot {
+    pub fn into_light(self) -> Box<dyn Light> {
+        match self {
+            LightSnapshot::Point(light) => Box::new(light),
+            LightSnapshot::Directional(light) => Box::new(light),
+            LightSnapshot::Spot(light) => Box::new(light),
+            LightSnapshot::Area(light) => Box::new(light),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 pub struct PointLight {
     intensity: Color,
     position: Point,
+    falloff_exponent: f64, // 0 is no falloff, 2 is physically correct
 }
 
 impl PointLight {
@@ -11,8 +52,39 @@ impl PointLight {
         PointLight {
             intensity,
             position,
+            falloff_exponent: 0.0,
         }
     }
+
+    pub fn with_falloff_exponent(mut self, exponent: f64) -> Self {
+        self.falloff_exponent = exponent;
+        self
+    }
+
+    // Tanner Helland's piecewise fit of blackbody color, valid roughly from 1000K to 40000K.
+    pub fn from_temperature(kelvin: f64, position: Point) -> Self {
+        let temp = kelvin / 100.0;
+        let red = if temp <= 66.0 {
+            255.0
+        } else {
+            329.698727446 * (temp - 60.0).powf(-0.1332047592)
+        };
+        let green = if temp <= 66.0 {
+            99.4708025861 * temp.ln() - 161.1195681661
+        } else {
+            288.1221695283 * (temp - 60.0).powf(-0.0755148492)
+        };
+        let blue = if temp >= 66.0 {
+            255.0
+        } else if temp <= 19.0 {
+            0.0
+        } else {
+            138.5177312231 * (temp - 10.0).ln() - 305.0447927307
+        };
+        let intensity = Color::new(red, green, blue).map(|c| c.clamp(0.0, 255.0) / 255.0);
+        PointLight::new(intensity, position)
+    }
+
     pub fn position(&self) -> Point{
         self.position
     }
@@ -21,6 +93,175 @@ impl PointLight {
     }
 }
 
+impl Light for PointLight {
+    fn sample_toward(&self, point: &Point) -> (Vector, f64, Color) {
+        let v = self.position - *point;
+        let distance = v.magnitude();
+        let intensity = self.intensity * distance.powf(-self.falloff_exponent);
+        (v * (1.0 / distance), distance, intensity)
+    }
+
+    #[cfg(feature = "snapshot")]
+    fn snapshot(&self) -> Option<LightSnapshot> {
+        Some(LightSnapshot::Point(self.clone()))
+    }
+}
+
+// Light from infinitely far away, like the sun: same direction and intensity everywhere.
+#[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
+pub struct DirectionalLight {
+    intensity: Color,
+    direction: Vector, // the way the light travels, unit length
+}
+
+impl DirectionalLight {
+    pub fn new(intensity: Color, direction: Vector) -> Self {
+        DirectionalLight {
+            intensity,
+            direction: direction.normalize(),
+        }
+    }
+
+    pub fn direction(&self) -> Vector {
+        self.direction
+    }
+
+    pub fn intensity(&self) -> Color {
+        self.intensity
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample_toward(&self, _point: &Point) -> (Vector, f64, Color) {
+        (-self.direction, f64::INFINITY, self.intensity)
+    }
+
+    #[cfg(feature = "snapshot")]
+    fn snapshot(&self) -> Option<LightSnapshot> {
+        Some(LightSnapshot::Directional(self.clone()))
+    }
+}
+
+// A point light restricted to a cone, fading out between the inner and outer angles.
+#[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpotLight {
+    intensity: Color,
+    position: Point,
+    direction: Vector, // axis of the cone, unit length
+    cos_inner: f64,
+    cos_outer: f64,
+}
+
+impl SpotLight {
+    // Angles are measured from the axis, in radians.
+    pub fn new(intensity: Color, position: Point, direction: Vector, inner_angle: f64, outer_angle: f64) -> Self {
+        SpotLight {
+            intensity,
+            position,
+            direction: direction.normalize(),
+            cos_inner: inner_angle.cos(),
+            cos_outer: outer_angle.max(inner_angle).cos(),
+        }
+    }
+
+    pub fn position(&self) -> Point {
+        self.position
+    }
+
+    pub fn intensity(&self) -> Color {
+        self.intensity
+    }
+}
+
+impl Light for SpotLight {
+    fn sample_toward(&self, point: &Point) -> (Vector, f64, Color) {
+        let v = self.position - *point;
+        let distance = v.magnitude();
+        let lightv = v * (1.0 / distance);
+        let cos_angle = (-lightv).dot_product(&self.direction);
+        let falloff = if cos_angle >= self.cos_inner {
+            1.0
+        } else if cos_angle <= self.cos_outer {
+            0.0
+        } else {
+            let t = (cos_angle - self.cos_outer) / (self.cos_inner - self.cos_outer);
+            t * t * (3.0 - 2.0 * t)
+        };
+        (lightv, distance, self.intensity * falloff)
+    }
+
+    #[cfg(feature = "snapshot")]
+    fn snapshot(&self) -> Option<LightSnapshot> {
+        Some(LightSnapshot::Spot(self.clone()))
+    }
+}
+
+// A rectangular light split into a grid of cells. Surfaces are lit from its center, while
+// shadow rays aim at every cell so the shadow fades by how much of the light is hidden.
+#[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
+pub struct AreaLight {
+    intensity: Color,
+    corner: Point,
+    uvec: Vector, // one cell along the first edge
+    usteps: usize,
+    vvec: Vector, // one cell along the second edge
+    vsteps: usize,
+}
+
+impl AreaLight {
+    // full_uvec and full_vvec are the rectangle's edges from corner.
+    pub fn new(
+        intensity: Color,
+        corner: Point,
+        full_uvec: Vector,
+        usteps: usize,
+        full_vvec: Vector,
+        vsteps: usize,
+    ) -> Self {
+        let (usteps, vsteps) = (usteps.max(1), vsteps.max(1));
+        AreaLight {
+            intensity,
+            corner,
+            uvec: full_uvec * (1.0 / usteps as f64),
+            usteps,
+            vvec: full_vvec * (1.0 / vsteps as f64),
+            vsteps,
+        }
+    }
+
+    pub fn position(&self) -> Point {
+        self.corner + self.uvec * (self.usteps as f64 / 2.0) + self.vvec * (self.vsteps as f64 / 2.0)
+    }
+
+    pub fn intensity(&self) -> Color {
+        self.intensity
+    }
+}
+
+impl Light for AreaLight {
+    fn sample_toward(&self, point: &Point) -> (Vector, f64, Color) {
+        let v = self.position() - *point;
+        let distance = v.magnitude();
+        (v * (1.0 / distance), distance, self.intensity)
+    }
+
+    // The center of each cell.
+    fn surface_points(&self) -> Vec<Point> {
+        (0..self.vsteps)
+            .flat_map(|v| (0..self.usteps).map(move |u| (u, v)))
+            .map(|(u, v)| self.corner + self.uvec * (u as f64 + 0.5) + self.vvec * (v as f64 + 0.5))
+            .collect()
+    }
+
+    #[cfg(feature = "snapshot")]
+    fn snapshot(&self) -> Option<LightSnapshot> {
+        Some(LightSnapshot::Area(self.clone()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -33,4 +274,76 @@ mod tests {
         assert_eq!(light.intensity(), intensity);
         assert_eq!(light.position(), position);
     }
+
+    #[test]
+    fn point_light_sample_points_at_light() {
+        let light = PointLight::new(Color::white(), Point::new(0.0, 3.0, 4.0));
+        let (direction, distance, intensity) = light.sample_toward(&Point::zero());
+        assert_eq!(direction, Vector::new(0.0, 0.6, 0.8));
+        assert_eq!(distance, 5.0);
+        assert_eq!(intensity, Color::white());
+    }
+
+    #[test]
+    fn directional_light_points_against_its_direction_everywhere() {
+        let light = DirectionalLight::new(Color::white(), Vector::new(0.0, -2.0, 0.0));
+        for point in [Point::zero(), Point::new(100.0, -5.0, 3.0)] {
+            let (direction, distance, intensity) = light.sample_toward(&point);
+            assert_eq!(direction, Vector::new(0.0, 1.0, 0.0));
+            assert!(distance.is_infinite());
+            assert_eq!(intensity, Color::white());
+        }
+    }
+
+    #[test]
+    fn spot_light_fades_between_inner_and_outer_cone() {
+        let light = SpotLight::new(
+            Color::white(),
+            Point::new(0.0, 10.0, 0.0),
+            Vector::new(0.0, -1.0, 0.0),
+            std::f64::consts::PI / 8.0,
+            std::f64::consts::PI / 4.0,
+        );
+        assert_eq!(light.sample_toward(&Point::zero()).2, Color::white());
+        let (_, _, edge) = light.sample_toward(&Point::new(7.0, 0.0, 0.0));
+        assert!(edge.red() > 0.0 && edge.red() < 1.0);
+        assert_eq!(light.sample_toward(&Point::new(20.0, 0.0, 0.0)).2, Color::black());
+    }
+
+    #[test]
+    fn area_light_samples_cell_centers() {
+        let light = AreaLight::new(
+            Color::white(),
+            Point::new(0.0, 5.0, 0.0),
+            Vector::new(2.0, 0.0, 0.0),
+            2,
+            Vector::new(0.0, 0.0, 1.0),
+            1,
+        );
+        assert_eq!(light.position(), Point::new(1.0, 5.0, 0.5));
+        assert_eq!(
+            light.surface_points(),
+            vec![Point::new(0.5, 5.0, 0.5), Point::new(1.5, 5.0, 0.5)]
+        );
+        let (direction, distance, _) = light.sample_toward(&Point::new(1.0, 0.0, 0.5));
+        assert_eq!(direction, Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(distance, 5.0);
+        assert!(PointLight::new(Color::white(), Point::zero()).surface_points().is_empty());
+    }
+
+    #[test]
+    fn daylight_temperature_is_near_white() {
+        let light = PointLight::from_temperature(6500.0, Point::zero());
+        let c = light.intensity();
+        assert!(c.red() > 0.95 && c.green() > 0.9 && c.blue() > 0.9);
+    }
+
+    #[test]
+    fn low_temperature_is_warm() {
+        let light = PointLight::from_temperature(2000.0, Point::zero());
+        let c = light.intensity();
+        assert_eq!(c.red(), 1.0);
+        assert!(c.green() / c.red() < 0.6);
+        assert!(c.blue() / c.red() < 0.2);
+    }
 }
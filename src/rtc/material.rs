@@ -1,17 +1,63 @@
 use crate::primitives::{Color, Point, Vector};
-use crate::rtc::{light::PointLight, pattern::Pattern};
+use crate::rtc::{light::Light, pattern::Pattern};
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq)]
+pub enum MaterialError {
+    OutOfRange { field: &'static str, value: f64 },
+    ReflectivePlusTransparencyAboveOne { reflective: f64, transparency: f64 },
+}
+
+impl std::fmt::Display for MaterialError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MaterialError::OutOfRange { field, value } => {
+                write!(f, "Material {field} out of range: {value}")
+            }
+            MaterialError::ReflectivePlusTransparencyAboveOne { reflective, transparency } => write!(
+                f,
+                "Material reflective ({reflective}) + transparency ({transparency}) exceeds 1"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MaterialError {}
+
+// Which secondary rays a material can spawn, cached on each object so shading can skip
+// straight past the reflection and refraction paths for plain surfaces.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
+pub enum SurfaceKind {
+    Diffuse,
+    Reflective,
+    Transparent,
+    ReflectiveTransparent, // blended by the Fresnel reflectance
+}
+
+impl SurfaceKind {
+    pub fn of(material: &Material) -> SurfaceKind {
+        match (material.reflective() > 0.0, material.transparency() > 0.0) {
+            (false, false) => SurfaceKind::Diffuse,
+            (true, false) => SurfaceKind::Reflective,
+            (false, true) => SurfaceKind::Transparent,
+            (true, true) => SurfaceKind::ReflectiveTransparent,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 pub struct Material {
-    pattern: Option<Pattern>,
-    color: Color,
+    pattern: Pattern, // solid unless with_pattern was given something else
     ambient: f64,
     diffuse: f64,
     specular: f64,
     shininess: f64,
     reflective: f64,
+    reflection_map: Option<Pattern>, // looked up by reflection direction instead of tracing
     transparency: f64,
     refractive_index: f64,
+    dispersion: f64,
     does_cast_shadow: bool,   
 }
 
@@ -20,18 +66,27 @@ impl Material {
         Default::default()
     }
 
+    // Patterned materials have no single color and report white.
     pub fn color(&self) -> Color {
-        self.color
+        self.pattern.solid_color().unwrap_or(Color::white())
     }
 
+    // None for a plain colored material.
     pub fn pattern(&self) -> Option<Pattern> {
-        Some(self.pattern)?
+        match self.pattern.solid_color() {
+            Some(_) => None,
+            None => Some(self.pattern.clone()),
+        }
     }
 
     pub fn reflective(&self) -> f64 {
         self.reflective
     }
 
+    pub fn reflection_map(&self) -> Option<&Pattern> {
+        self.reflection_map.as_ref()
+    }
+
     pub fn transparency(&self) -> f64 {
         self.transparency
     }
@@ -40,6 +95,19 @@ impl Material {
         self.refractive_index
     }
 
+    pub fn dispersion(&self) -> f64 {
+        self.dispersion
+    }
+
+    // Index seen by each color channel; red bends the least and blue the most.
+    pub fn channel_refractive_indices(&self) -> [f64; 3] {
+        [
+            self.refractive_index - self.dispersion,
+            self.refractive_index,
+            self.refractive_index + self.dispersion,
+        ]
+    }
+
     pub fn does_cast_shadow(&self) -> bool {
         self.does_cast_shadow
     }
@@ -54,6 +122,11 @@ impl Material {
         self
     }
 
+    pub fn with_dispersion(mut self, dispersion: f64) -> Self {
+        self.dispersion = dispersion;
+        self
+    }
+
     pub fn with_ambient(mut self, ambient: f64) -> Self {
         self.ambient = ambient;
         self
@@ -74,11 +147,11 @@ impl Material {
     }
 
     pub fn with_color(mut self, color: Color) -> Self {
-        self.color = color;
+        self.pattern = Pattern::new_solid(color);
         self
     }
     pub fn with_pattern(mut self, pattern: Pattern) -> Self {
-        self.pattern = Some(pattern);
+        self.pattern = pattern;
         self
     }
 
@@ -87,30 +160,100 @@ impl Material {
         self
     }
 
+    // Clear glass-like material: fully reflective and fully transparent, leaving the split to the
+    // Schlick reflectance at each hit, which is ((ior - 1) / (ior + 1))² head on and 1 at grazing.
+    pub fn with_dielectric(mut self, ior: f64) -> Self {
+        self.transparency = 1.0;
+        self.refractive_index = ior;
+        self.reflective = 1.0;
+        self
+    }
+
+    fn is_dielectric(&self) -> bool {
+        self.reflective == 1.0 && self.transparency == 1.0
+    }
+
+    pub fn with_reflection_map(mut self, map: Pattern) -> Self {
+        self.reflection_map = Some(map);
+        self
+    }
+
     pub fn with_shadow(mut self, shadow: bool) -> Self{
         self.does_cast_shadow = shadow;
         self
     }
 
 
+    // Checks coefficients for values that would make a surface emit more light than it receives.
+    pub fn validate(&self) -> Result<(), MaterialError> {
+        let unit_fields = [
+            ("ambient", self.ambient),
+            ("diffuse", self.diffuse),
+            ("specular", self.specular),
+            ("reflective", self.reflective),
+            ("transparency", self.transparency),
+        ];
+        for (field, value) in unit_fields {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(MaterialError::OutOfRange { field, value });
+            }
+        }
+        let positive_fields = [
+            ("shininess", self.shininess),
+            ("refractive_index", self.refractive_index),
+        ];
+        for (field, value) in positive_fields {
+            if value.is_nan() || value <= 0.0 {
+                return Err(MaterialError::OutOfRange { field, value });
+            }
+        }
+        if self.dispersion.is_nan() || self.dispersion < 0.0 {
+            return Err(MaterialError::OutOfRange {
+                field: "dispersion",
+                value: self.dispersion,
+            });
+        }
+        // a full dielectric splits each hit between the two by the Fresnel reflectance
+        if self.reflective + self.transparency > 1.0 && !self.is_dielectric() {
+            return Err(MaterialError::ReflectivePlusTransparencyAboveOne {
+                reflective: self.reflective,
+                transparency: self.transparency,
+            });
+        }
+        Ok(())
+    }
+
     pub fn lighting(
         &self,
-        light: &PointLight,
+        light: &dyn Light,
         object_point: &Point,
         world_point: &Point,
         eyev: &Vector,
         normalv: &Vector,
         in_shadow: bool,
     ) -> Color {
-        let color = match self.pattern {
-            Some(pattern) => pattern.pattern_at(object_point),
-            None => self.color,
-        };
-        let effective_color = color * light.intensity();
-        let lightv = (light.position() - *world_point).normalize();
+        let shadow = if in_shadow { 1.0 } else { 0.0 };
+        self.lighting_with_shadow(light, object_point, world_point, eyev, normalv, shadow)
+    }
+
+    // Like lighting, but with a shadow amount from 0 (fully lit) to 1 (fully shadowed)
+    // that scales down the diffuse and specular terms.
+    pub fn lighting_with_shadow(
+        &self,
+        light: &dyn Light,
+        object_point: &Point,
+        world_point: &Point,
+        eyev: &Vector,
+        normalv: &Vector,
+        shadow: f64,
+    ) -> Color {
+        let color = self.pattern.pattern_at(object_point);
+        let (lightv, _, intensity) = light.sample_toward(world_point);
+        let effective_color = color * intensity;
         let ambient = effective_color * self.ambient;
         let light_dot_normal = lightv.dot_product(normalv);
-        let (diffuse, specular) = if light_dot_normal < 0.0 || (in_shadow && self.does_cast_shadow()) {
+        let lit = if self.does_cast_shadow() { 1.0 - shadow } else { 1.0 };
+        let (diffuse, specular) = if light_dot_normal < 0.0 || lit <= 0.0 {
             (Color::new(0.0, 0.0, 0.0), Color::new(0.0, 0.0, 0.0))
         } else {
             let diffuse = effective_color * self.diffuse * light_dot_normal;
@@ -120,9 +263,9 @@ impl Material {
                 Color::new(0.0, 0.0, 0.0)
             } else {
                 let factor = reflect_dot_eye.powf(self.shininess);
-                light.intensity() * self.specular * factor
+                intensity * self.specular * factor
             };
-            (diffuse, specular)
+            (diffuse * lit, specular * lit)
         };
         ambient + diffuse + specular
     }
@@ -131,15 +274,16 @@ impl Material {
 impl Default for Material {
     fn default() -> Self {
         Material {
-            color: Color::new(1.0, 1.0, 1.0),
             ambient: 0.1,
             diffuse: 0.9,
             specular: 0.9,
             shininess: 200.0,
-            pattern: None,
+            pattern: Pattern::new_solid(Color::new(1.0, 1.0, 1.0)),
             reflective: 0.0,
+            reflection_map: None,
             transparency: 0.0,
             refractive_index: 1.0,
+            dispersion: 0.0,
             does_cast_shadow: true,
         }
     }
@@ -149,10 +293,11 @@ impl Default for Material {
 mod tests {
     use super::*;
     use crate::primitives::Tuple;
+    use crate::rtc::light::PointLight;
     #[test]
     fn test_material() {
         let m = Material::new();
-        assert_eq!(m.color, Color::new(1.0, 1.0, 1.0));
+        assert_eq!(m.color(), Color::new(1.0, 1.0, 1.0));
         assert_eq!(m.ambient, 0.1);
         assert_eq!(m.diffuse, 0.9);
         assert_eq!(m.specular, 0.9);
@@ -170,6 +315,51 @@ mod tests {
         assert_eq!(result, Color::new(1.9, 1.9, 1.9));
     }
 
+    #[test]
+    fn lighting_with_light_trait_object() {
+        #[derive(Debug)]
+        struct HeadOn;
+        impl Light for HeadOn {
+            fn sample_toward(&self, _point: &Point) -> (Vector, f64, Color) {
+                (Vector::new(0.0, 0.0, -1.0), f64::INFINITY, Color::new(0.5, 0.5, 0.5))
+            }
+        }
+        let m = Material::new();
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let lights: Vec<Box<dyn Light>> = vec![
+            Box::new(PointLight::new(Color::new(1.0, 1.0, 1.0), Point::new(0.0, 0.0, -10.0))),
+            Box::new(HeadOn),
+        ];
+        let results: Vec<Color> = lights
+            .iter()
+            .map(|light| m.lighting(light.as_ref(), &position, &position, &eyev, &normalv, false))
+            .collect();
+        assert_eq!(results[0], Color::new(1.9, 1.9, 1.9));
+        assert_eq!(results[1], Color::new(0.95, 0.95, 0.95));
+    }
+
+    #[test]
+    fn lighting_with_falloff_exponent() {
+        let m = Material::new()
+            .with_ambient(0.0)
+            .with_diffuse(1.0)
+            .with_specular(0.0);
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        for (exponent, ratio) in [(0.0, 1.0), (1.0, 0.5), (2.0, 0.25)] {
+            let at = |distance: f64| {
+                let light = PointLight::new(Color::white(), Point::new(0.0, 0.0, -distance))
+                    .with_falloff_exponent(exponent);
+                m.lighting(&light, &position, &position, &eyev, &normalv, false)
+            };
+            assert_eq!(at(1.0), Color::white());
+            assert_eq!(at(2.0), Color::white() * ratio);
+        }
+    }
+
     #[test]
     fn lighting_with_eye_between_light_and_surface_eye_offset_45() {
         let m = Material::new();
@@ -203,6 +393,31 @@ mod tests {
         assert_eq!(result, Color::new(1.6364, 1.6364, 1.6364));
     }
 
+    #[test]
+    fn material_without_pattern_has_none() {
+        assert_eq!(Material::new().pattern(), None);
+        assert_eq!(Material::new().with_color(Color::black()).pattern(), None);
+        let stripes = Pattern::new_stripe(Color::white(), Color::black());
+        assert_eq!(Material::new().with_pattern(stripes.clone()).pattern(), Some(stripes));
+    }
+
+    #[test]
+    fn with_color_lights_like_a_solid_pattern() {
+        let color = Color::new(0.2, 0.6, 0.9);
+        let colored = Material::new().with_color(color);
+        let solid = Material::new().with_pattern(Pattern::new_solid(color));
+        assert_eq!(colored, solid);
+        assert_eq!(solid.color(), color);
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Color::white(), Point::new(0.0, 10.0, -10.0));
+        assert_eq!(
+            colored.lighting(&light, &position, &position, &eyev, &normalv, false),
+            solid.lighting(&light, &position, &position, &eyev, &normalv, false)
+        );
+    }
+
     #[test]
     fn lighting_with_light_behind_surface() {
         let m = Material::new();
@@ -229,7 +444,7 @@ mod tests {
     #[test]
     fn lighting_with_pattern_applied() {
         let mut m = Material::new();
-        m.pattern = Some(Pattern::new_stripe(Color::new(1.0, 1.0, 1.0), Color::new(0.0, 0.0, 0.0)));
+        m.pattern = Pattern::new_stripe(Color::new(1.0, 1.0, 1.0), Color::new(0.0, 0.0, 0.0));
         m.ambient = 1.0;
         m.diffuse = 0.0;
         m.specular = 0.0;
@@ -243,4 +458,46 @@ mod tests {
         assert_eq!(c1, Color::new(1.0, 1.0, 1.0));
         assert_eq!(c2, Color::new(0.0, 0.0, 0.0));
     }
+
+    #[test]
+    fn dielectric_reflectance_follows_refractive_index() {
+        let glass = Material::new().with_dielectric(1.5);
+        assert_eq!(glass.transparency(), 1.0);
+        assert_eq!(glass.refractive_index(), 1.5);
+        assert_eq!(glass.reflective(), 1.0);
+        assert_eq!(glass.validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_accepts_sane_materials() {
+        assert_eq!(Material::new().validate(), Ok(()));
+        let glass = Material::new()
+            .with_reflective(0.5)
+            .with_transparency(0.5)
+            .with_refractive_index(1.5);
+        assert_eq!(glass.validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_rejects_out_of_range_materials() {
+        assert_eq!(
+            Material::new().with_reflective(5.0).validate(),
+            Err(MaterialError::OutOfRange { field: "reflective", value: 5.0 })
+        );
+        assert_eq!(
+            Material::new().with_ambient(-0.1).validate(),
+            Err(MaterialError::OutOfRange { field: "ambient", value: -0.1 })
+        );
+        assert_eq!(
+            Material::new().with_shininess(0.0).validate(),
+            Err(MaterialError::OutOfRange { field: "shininess", value: 0.0 })
+        );
+        assert_eq!(
+            Material::new().with_reflective(0.7).with_transparency(0.6).validate(),
+            Err(MaterialError::ReflectivePlusTransparencyAboveOne {
+                reflective: 0.7,
+                transparency: 0.6
+            })
+        );
+    }
 }
@@ -1,32 +1,46 @@
 use crate::{
-    primitives::{Matrix, Point, Vector},
-    rtc::shape::Shape,
+    float::rng::Rng,
+    primitives::{Matrix, Point, Tuple, Vector},
+    rtc::{bounds::BoundingBox, shape::Shape, shapes::mesh::TriangleMesh},
 };
+use std::sync::Arc;
+
+use super::{config::RenderConfig, intersection::{Intersection, Intersections}, material::{Material, SurfaceKind}, ray::Ray};
+
+#[cfg(test)]
+thread_local! {
+    // Shape-level intersection tests run on this thread, so tests can see when one is skipped.
+    static SHAPE_TESTS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
 
-use super::{intersection::Intersections, material::Material, ray::Ray};
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 pub struct Object {
     shape: Shape,
     transform: Matrix,
     transform_inverse: Matrix,
     transform_inverse_transpose: Matrix,
     material: Material,
+    surface_kind: SurfaceKind, // follows the material
+    world_bounds: BoundingBox, // cached, follows the transform
 }
 
-impl<'a> Object {
-    pub fn new_sphere() -> Self {
+impl Object {
+    fn from_shape(shape: Shape) -> Self {
+        let world_bounds = shape.bounds();
         Object {
-            shape: Shape::Sphere,
+            shape,
+            world_bounds,
             ..Default::default()
         }
     }
 
+    pub fn new_sphere() -> Self {
+        Object::from_shape(Shape::Sphere)
+    }
+
     pub fn new_glass_sphere() -> Self {
-        Object {
-            shape: Shape::Sphere,
-            ..Default::default()
-        }
-        .set_material(
+        Object::new_sphere().set_material(
             &Material::new()
                 .with_transparency(1.0)
                 .with_refractive_index(1.5),
@@ -38,64 +52,160 @@ impl<'a> Object {
     }
 
     pub fn new_cylinder(minimum: f64, maximum: f64) -> Self {
-        Object {
-            shape: Shape::Cylinder(minimum, maximum, false),
-            ..Default::default()
-        }
+        Object::from_shape(Shape::Cylinder(minimum, maximum, false))
     }
     pub fn new_closed_cylinder(minimum: f64, maximum: f64) -> Self {
-        Object {
-            shape: Shape::Cylinder(minimum, maximum, true),
-            ..Default::default()
-        }
+        Object::from_shape(Shape::Cylinder(minimum, maximum, true))
     }
     pub fn new_closed_cone(minimum: f64, maximum: f64) -> Self {
-        Object {
-            shape: Shape::Cylinder(minimum, maximum, true),
-            ..Default::default()
-        }
+        Object::from_shape(Shape::Cone(minimum, maximum, true))
     }
 
     pub fn new_cone(minimum: f64, maximum: f64) -> Self {
-        Object {
-            shape: Shape::Cone(minimum, maximum, false),
-            ..Default::default()
-        }
+        Object::from_shape(Shape::Cone(minimum, maximum, false))
     }
 
     pub fn new_plane() -> Self {
-        Object {
-            shape: Shape::Plane,
-            ..Default::default()
-        }
+        Object::from_shape(Shape::Plane(false))
+    }
+
+    pub fn new_flipped_plane() -> Self {
+        Object::from_shape(Shape::Plane(true))
+    }
+
+    pub fn new_disk(radius: f64) -> Self {
+        Object::from_shape(Shape::Disk(radius))
+    }
+
+    pub fn new_mesh(vertices: Vec<Point>, faces: Vec<[usize; 3]>) -> Self {
+        Object::from_shape(Shape::Mesh(Arc::new(TriangleMesh::new(vertices, faces))))
     }
 
     pub fn new_cube() -> Self {
-        Object {
-            shape: Shape::Cube,
-            ..Default::default()
+        Object::new_box(1.0, 1.0, 1.0)
+    }
+
+    // A box spanning [-x, x], [-y, y] and [-z, z] in object space.
+    pub fn new_box(x: f64, y: f64, z: f64) -> Self {
+        Object::from_shape(Shape::Cube(x, y, z))
+    }
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    pub fn surface_kind(&self) -> SurfaceKind {
+        self.surface_kind
+    }
+
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+    // Sorted by ascending t, whatever order the shape found them in.
+    pub fn intersect(&self, ray: &Ray) -> Intersections<'_> {
+        self.intersect_with_config(ray, &RenderConfig::default())
+    }
+
+    pub fn intersect_with_config(&self, ray: &Ray, config: &RenderConfig) -> Intersections<'_> {
+        if self.shape.has_expensive_intersection() && self.ray_misses_bounding_sphere(ray) {
+            return Intersections::new();
         }
+        #[cfg(test)]
+        SHAPE_TESTS.with(|n| n.set(n.get() + 1));
+        let transformed_ray = ray.transform(&self.transform_inverse);
+        self.shape
+            .intersect_with_epsilon(&transformed_ray, self, config.parallel_epsilon)
+            .sort()
+    }
+
+    pub fn nearest_intersection(&self, ray: &Ray) -> Option<Intersection<'_>> {
+        self.nearest_intersection_with_config(ray, &RenderConfig::default())
     }
-    pub fn material(&self) -> Material {
-        self.material
+
+    pub fn nearest_intersection_with_config(&self, ray: &Ray, config: &RenderConfig) -> Option<Intersection<'_>> {
+        self.nearest_intersection_before(ray, f64::INFINITY, config)
     }
 
-    pub fn shape(&self) -> Shape {
+    // The hit intersect(ray).hit() would give if it lies before t_max, found from the shape's
+    // roots directly rather than through an Intersections list.
+    pub fn nearest_intersection_before(
+        &self,
+        ray: &Ray,
+        t_max: f64,
+        config: &RenderConfig,
+    ) -> Option<Intersection<'_>> {
+        if self.shape.has_expensive_intersection() && self.ray_misses_bounding_sphere(ray) {
+            return None;
+        }
+        #[cfg(test)]
+        SHAPE_TESTS.with(|n| n.set(n.get() + 1));
+        let transformed_ray = ray.transform(&self.transform_inverse);
         self.shape
+            .nearest_intersection(&transformed_ray, self, t_max, config.parallel_epsilon)
     }
-    pub fn intersect(&self, ray: &'a Ray) -> Intersections {
+
+    // Rays that miss the world bounds skip the shape's own test, which stops at the first root
+    // before t_max instead of listing them all.
+    pub fn any_hit(&self, ray: &Ray, t_max: f64, config: &RenderConfig) -> bool {
+        if !self.world_bounds.hit_by(ray, t_max) {
+            return false;
+        }
         let transformed_ray = ray.transform(&self.transform_inverse);
-        self.shape.intersect(&transformed_ray, self)
+        self.shape.any_hit(&transformed_ray, t_max, config.parallel_epsilon)
     }
 
-    pub fn set_transform(mut self, transform: &Matrix) -> Self {
-        self.transform = *transform;
-        self.transform_inverse = (*transform).inverse().unwrap();
+    pub fn bounding_sphere(&self) -> (Point, f64) {
+        let center = self.transform * Point::zero();
+        // sqrt(|M|_1 * |M|_inf) bounds how much the linear part can stretch a vector
+        let max_column = (0..3)
+            .map(|j| (0..3).map(|i| self.transform[(i, j)].abs()).sum::<f64>())
+            .fold(0.0, f64::max);
+        let max_row = (0..3)
+            .map(|i| (0..3).map(|j| self.transform[(i, j)].abs()).sum::<f64>())
+            .fold(0.0, f64::max);
+        let radius = self.shape.bounding_radius() * (max_column * max_row).sqrt();
+        (center, radius)
+    }
+
+    // Largest factor the transform stretches a unit vector along any object axis.
+    pub fn transform_scale(&self) -> f64 {
+        (0..3)
+            .map(|j| {
+                (0..3)
+                    .map(|i| self.transform[(i, j)].powi(2))
+                    .sum::<f64>()
+                    .sqrt()
+            })
+            .fold(0.0, f64::max)
+    }
+
+    pub fn ray_misses_bounding_sphere(&self, ray: &Ray) -> bool {
+        let (center, radius) = self.bounding_sphere();
+        if radius.is_infinite() {
+            return false;
+        }
+        let to_center = center - ray.origin();
+        let direction = ray.direction().normalize();
+        let along = to_center.dot_product(&direction);
+        let distance_squared = to_center.magnitude_squared() - along * along;
+        distance_squared > radius * radius
+    }
+
+    pub fn set_transform(mut self, transform: impl Into<Matrix>) -> Self {
+        let transform = transform.into();
+        self.transform = transform;
+        self.transform_inverse = transform.inverse().unwrap();
         self.transform_inverse_transpose = self.transform_inverse.transpose();
+        self.world_bounds = self.shape.bounds().transform(&self.transform);
         self
     }
+    // Same shape and material somewhere else, for placing copies of one object around a scene.
+    pub fn clone_with_transform(&self, transform: &Matrix) -> Object {
+        self.clone().set_transform(transform)
+    }
+
     pub fn set_material(mut self, material: &Material) -> Self {
-        self.material = *material;
+        self.material = material.clone();
+        self.surface_kind = SurfaceKind::of(material);
         self
     }
     pub fn normal_at(&self, world_point: &Point) -> Vector {
@@ -105,6 +215,40 @@ impl<'a> Object {
         world_normal.normalize()
     }
 
+    /// A random point on the surface and its normal, in world space. Points are uniform over
+    /// the object space surface, so a non-uniform scale makes them bunch up along the short axes.
+    ///
+    /// ```
+    /// use ray_tracer::primitives::{Matrix, Point, Tuple};
+    /// use ray_tracer::rtc::object::Object;
+    /// use ray_tracer::Rng;
+    ///
+    /// let emitter = Object::new_sphere().set_transform(Matrix::id().translate(0.0, 5.0, 0.0));
+    /// let mut rng = Rng::new(42);
+    /// let (point, normal) = emitter.sample_surface(&mut rng);
+    /// let from_center = point - Point::new(0.0, 5.0, 0.0);
+    /// assert!((from_center.magnitude() - 1.0).abs() < 1e-9);
+    /// assert!((normal.magnitude() - 1.0).abs() < 1e-9);
+    /// ```
+    pub fn sample_surface(&self, rng: &mut Rng) -> (Point, Vector) {
+        let (point, normal) = self.shape.sample_surface(rng);
+        (
+            self.transform * point,
+            (self.transform_inverse_transpose * normal).normalize(),
+        )
+    }
+
+    // Ignores the cached inverses, whose rounding depends on how the transform was built.
+    pub fn approx_eq(&self, other: &Object) -> bool {
+        self.shape == other.shape
+            && self.material == other.material
+            && self.transform == other.transform
+    }
+
+    pub fn world_bounds(&self) -> &BoundingBox {
+        &self.world_bounds
+    }
+
     pub fn transform(&self) -> &Matrix {
         &self.transform
     }
@@ -121,6 +265,8 @@ impl Default for Object {
             transform_inverse: Matrix::id(),
             transform_inverse_transpose: Matrix::id(),
             material: Material::new(),
+            surface_kind: SurfaceKind::Diffuse,
+            world_bounds: Shape::Sphere.bounds(),
         }
     }
 }
@@ -128,7 +274,19 @@ impl Default for Object {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::primitives::Tuple;
+    use crate::float::ApproxEq;
+    #[test]
+    fn sphere_surface_samples_lie_on_unit_sphere() {
+        let sphere = Object::new_sphere();
+        let mut rng = Rng::new(1);
+        for _ in 0..1000 {
+            let (point, normal) = sphere.sample_surface(&mut rng);
+            let object_point = sphere.to_object_space(&point);
+            assert!((object_point - Point::zero()).magnitude().approx_eq(1.0));
+            assert_eq!(normal, object_point - Point::zero());
+        }
+    }
+
     #[test]
     fn intersection() {
         let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
@@ -187,7 +345,7 @@ mod tests {
     fn change_sphere_transform() {
         let mut sphere = Object::new_sphere();
         let transform = Matrix::id().translate(2.0, 3.0, 4.0);
-        sphere = sphere.set_transform(&transform);
+        sphere = sphere.set_transform(transform);
         assert_eq!(sphere.transform, transform);
     }
 
@@ -196,7 +354,7 @@ mod tests {
         let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let mut sphere = Object::new_sphere();
         let transform = Matrix::id().scale(2.0, 2.0, 2.0);
-        sphere = sphere.set_transform(&transform);
+        sphere = sphere.set_transform(transform);
         assert_eq!(sphere.transform, transform);
         let intersections = sphere.intersect(&ray);
         assert_eq!(intersections.count(), 2);
@@ -204,14 +362,177 @@ mod tests {
         assert_eq!(intersections[1].t(), 7.0);
     }
 
+    #[test]
+    fn bounding_sphere_in_world_space() {
+        let sphere = Object::new_sphere()
+            .set_transform(Matrix::id().scale(2.0, 2.0, 2.0).translate(1.0, 2.0, 3.0));
+        let (center, radius) = sphere.bounding_sphere();
+        assert_eq!(center, Point::new(1.0, 2.0, 3.0));
+        assert!(radius.approx_eq(2.0));
+        let (_, radius) = Object::new_plane().bounding_sphere();
+        assert!(radius.is_infinite());
+    }
+
+    #[test]
+    fn ray_outside_bounding_sphere_is_rejected() {
+        let cone = Object::new_closed_cone(-1.0, 1.0);
+        let shape_tests = || SHAPE_TESTS.with(|n| n.get());
+        let far = Ray::new(Point::new(5.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(cone.ray_misses_bounding_sphere(&far));
+        let before = shape_tests();
+        assert_eq!(cone.intersect(&far).count(), 0);
+        assert_eq!(cone.nearest_intersection(&far), None);
+        assert_eq!(shape_tests(), before);
+        let through = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(!cone.ray_misses_bounding_sphere(&through));
+        assert_eq!(cone.intersect(&through).count(), 2);
+        assert!(cone.nearest_intersection(&through).is_some());
+        assert_eq!(shape_tests(), before + 2);
+    }
+
     #[test]
     fn intersect_translated_sphere_with_ray() {
         let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let mut sphere = Object::new_sphere();
         let transform = Matrix::id().translate(5.0, 0.0, 0.0);
-        sphere = sphere.set_transform(&transform);
+        sphere = sphere.set_transform(transform);
         assert_eq!(sphere.transform, transform);
         let intersections = sphere.intersect(&ray);
         assert_eq!(intersections.count(), 0);
     }
+
+    #[test]
+    fn set_transform_accepts_builder() {
+        use crate::rtc::transformation::Transform;
+        let built = Object::new_sphere()
+            .set_transform(Transform::new().scale(2.0, 2.0, 2.0).translate(1.0, 0.0, 0.0));
+        let explicit = Object::new_sphere()
+            .set_transform(Matrix::id().scale(2.0, 2.0, 2.0).translate(1.0, 0.0, 0.0));
+        assert_eq!(built, explicit);
+    }
+
+    #[test]
+    fn intersections_come_back_sorted() {
+        let cylinder = Object::new_closed_cylinder(1.0, 2.0);
+        let ray = Ray::new(Point::new(0.0, 3.0, -2.0), Vector::new(0.0, -1.0, 2.0));
+        let xs = cylinder.intersect(&ray);
+        assert_eq!(xs.count(), 2);
+        assert!(xs.iter().zip(xs.iter().skip(1)).all(|(a, b)| a.t() <= b.t()));
+    }
+
+    #[test]
+    fn transform_scale_is_largest_axis_stretch() {
+        assert_eq!(Object::new_sphere().transform_scale(), 1.0);
+        let stretched = Object::new_sphere()
+            .set_transform(Matrix::id().scale(2.0, 5.0, 1.0).rotate_y(0.7).translate(9.0, 0.0, 0.0));
+        assert!(stretched.transform_scale().approx_eq(5.0));
+    }
+
+    #[test]
+    fn equivalent_transform_chains_are_approx_equal() {
+        use std::f64::consts::PI;
+        let a = Object::new_cube().set_transform(
+            Matrix::id()
+                .rotate_y(PI / 3.0)
+                .rotate_y(PI / 6.0)
+                .scale(3.0, 3.0, 3.0)
+                .translate(0.1, 0.2, 0.3),
+        );
+        let b = Object::new_cube().set_transform(
+            Matrix::id()
+                .scale(1.5, 1.5, 1.5)
+                .rotate_y(PI / 2.0)
+                .translate(0.05, 0.1, 0.15)
+                .scale(2.0, 2.0, 2.0),
+        );
+        assert!(a.approx_eq(&b));
+        assert!(!a.approx_eq(&Object::new_sphere().set_transform(*b.transform())));
+    }
+
+    #[test]
+    fn world_bounds_follow_the_transform() {
+        let cube = Object::new_cube();
+        assert_eq!(cube.world_bounds(), &cube.shape().bounds());
+        let transform = Matrix::id().scale(2.0, 1.0, 1.0).translate(5.0, 0.0, 0.0);
+        let moved = cube.set_transform(transform);
+        assert_eq!(moved.world_bounds().min(), Point::new(3.0, -1.0, -1.0));
+        assert_eq!(moved.world_bounds().max(), Point::new(7.0, 1.0, 1.0));
+        assert_eq!(moved.world_bounds(), &moved.shape().bounds().transform(&transform));
+        let plane = Object::new_plane().set_transform(Matrix::id().translate(0.0, 2.0, 0.0));
+        assert_eq!(plane.world_bounds().min().y(), 2.0);
+        assert!(plane.world_bounds().max().x().is_infinite());
+    }
+
+    #[test]
+    fn clone_with_transform_keeps_material() {
+        let glass = Object::new_glass_sphere();
+        let moved = glass.clone_with_transform(&Matrix::id().translate(3.0, 0.0, 0.0));
+        assert_eq!(moved.material(), glass.material());
+        assert_eq!(moved.shape(), glass.shape());
+        assert_eq!(*moved.transform(), Matrix::id().translate(3.0, 0.0, 0.0));
+        assert_eq!(moved.to_object_space(&Point::new(3.0, 0.0, 0.0)), Point::zero());
+    }
+
+    #[test]
+    fn nearest_intersection_matches_sorted_hit_for_every_shape() {
+        let shapes = [
+            Object::new_sphere(),
+            Object::new_plane(),
+            Object::new_cube(),
+            Object::new_box(2.0, 0.5, 1.0),
+            Object::new_cylinder(-1.0, 1.0),
+            Object::new_closed_cylinder(-1.0, 1.0),
+            Object::new_closed_cone(-1.0, 1.0),
+            Object::new_disk(1.0),
+            Object::new_mesh(
+                vec![Point::new(0.0, 1.0, 0.0), Point::new(-1.0, 0.0, 0.0), Point::new(1.0, 0.0, 0.0)],
+                vec![[0, 1, 2]],
+            ),
+        ];
+        let rays = [
+            Ray::new(Point::new(0.1, 0.2, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.3, 0.4, 0.5)),
+            Ray::new(Point::new(0.3, 5.0, 0.1), Vector::new(0.0, -1.0, 0.0)),
+            Ray::new(Point::new(-3.0, 0.5, -3.0), Vector::new(1.0, -0.2, 1.0)),
+            Ray::new(Point::new(5.0, 5.0, 5.0), Vector::new(1.0, 0.0, 0.0)),
+        ];
+        for shape in &shapes {
+            let shape = shape.clone().set_transform(Matrix::id().rotate_x(0.3).translate(0.1, 0.0, 0.2));
+            for r in &rays {
+                let xs = shape.intersect(r);
+                let expected = xs.hit();
+                let nearest = shape.nearest_intersection(r);
+                assert_eq!(nearest.as_ref(), expected, "{:?} {:?}", shape.shape(), r);
+                for t_max in [1.0, 5.0, f64::INFINITY] {
+                    let full = xs.iter().any(|i| i.t() >= 0.0 && i.t() < t_max);
+                    assert_eq!(shape.any_hit(r, t_max, &RenderConfig::default()), full, "{:?} {:?}", shape.shape(), r);
+                }
+                if let Some(hit) = expected {
+                    // nothing lies before the nearest hit
+                    let config = RenderConfig::default();
+                    assert_eq!(shape.nearest_intersection_before(r, hit.t(), &config), None);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn any_hit_through_and_past_sphere() {
+        let sphere = Object::new_sphere();
+        let config = RenderConfig::default();
+        let through = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(sphere.any_hit(&through, f64::INFINITY, &config));
+        assert!(!sphere.any_hit(&through, 3.0, &config));
+        let miss = Ray::new(Point::new(0.0, 2.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(!sphere.any_hit(&miss, f64::INFINITY, &config));
+    }
+    #[test]
+    fn set_material_updates_surface_kind() {
+        let sphere = Object::new_sphere();
+        assert_eq!(sphere.surface_kind(), SurfaceKind::Diffuse);
+        let glass = sphere.set_material(&Material::new().with_transparency(1.0));
+        assert_eq!(glass.surface_kind(), SurfaceKind::Transparent);
+        let plain = glass.set_material(&Material::new());
+        assert_eq!(plain.surface_kind(), SurfaceKind::Diffuse);
+    }
 }
@@ -1,9 +1,10 @@
 use crate::{
     float::ApproxEq,
-    primitives::{Color, Matrix, Point, Tuple},
+    primitives::{Color, Matrix, Point, Tuple, Vector},
 };
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pattern {
     pattern_type: PatternType,
     transform: Matrix,
@@ -18,6 +19,13 @@ impl Pattern {
         }
     }
 
+    pub fn new_solid(color: Color) -> Pattern {
+        Pattern {
+            pattern_type: PatternType::Solid(SolidPattern { color }),
+            ..Default::default()
+        }
+    }
+
     pub fn new_stripe(a: Color, b: Color) -> Pattern {
         Pattern {
             pattern_type: PatternType::Stripe(StripePattern { a, b }),
@@ -26,15 +34,29 @@ impl Pattern {
     }
 
     pub fn new_gradient(a: Color, b: Color) -> Pattern {
+        Pattern::new_gradient_along(a, b, Vector::new(1.0, 0.0, 0.0))
+    }
+
+    // Blends from a to b as point · direction goes from 0 to 1.
+    pub fn new_gradient_along(a: Color, b: Color, direction: Vector) -> Pattern {
         Pattern {
-            pattern_type: PatternType::Gradient(GradientPattern { a, b }),
+            pattern_type: PatternType::Gradient(GradientPattern { a, b, direction }),
             ..Default::default()
         }
     }
 
     pub fn new_radial_gradient(a: Color, b: Color) -> Pattern {
+        Pattern::new_radial_gradient_around(a, b, Vector::new(0.0, 1.0, 0.0))
+    }
+
+    // Blends outward from the axis, in planes perpendicular to it.
+    pub fn new_radial_gradient_around(a: Color, b: Color, axis: Vector) -> Pattern {
         Pattern {
-            pattern_type: PatternType::RadialGradient(RadialGradientPattern { a, b }),
+            pattern_type: PatternType::RadialGradient(RadialGradientPattern {
+                a,
+                b,
+                axis: axis.normalize(),
+            }),
             ..Default::default()
         }
     }
@@ -48,29 +70,67 @@ impl Pattern {
 
     pub fn new_checkers(a: Color, b: Color) -> Pattern {
         Pattern {
-            pattern_type: PatternType::Checkers(CheckersPattern { a, b }),
+            pattern_type: PatternType::Checkers(CheckersPattern {
+                a,
+                b,
+                mode: CheckersMode::Spatial,
+            }),
+            ..Default::default()
+        }
+    }
+
+    pub fn new_checkers_with_mode(a: Color, b: Color, mode: CheckersMode) -> Pattern {
+        Pattern {
+            pattern_type: PatternType::Checkers(CheckersPattern { a, b, mode }),
+            ..Default::default()
+        }
+    }
+
+    pub fn new_product(a: Pattern, b: Pattern) -> Pattern {
+        Pattern {
+            pattern_type: PatternType::Product(ProductPattern {
+                a: Box::new(a),
+                b: Box::new(b),
+            }),
             ..Default::default()
         }
     }
 
     pub fn pattern_at(&self, object_point: &Point) -> Color {
         let pattern_point = self.to_pattern_space(object_point);
-        match self.pattern_type {
+        match &self.pattern_type {
+            PatternType::Solid(p) => p.pattern_at(&pattern_point),
             PatternType::Stripe(p) => p.pattern_at(&pattern_point),
             PatternType::Test(p) => p.pattern_at(&pattern_point),
             PatternType::Gradient(p) => p.pattern_at(&pattern_point),
             PatternType::Ring(p) => p.pattern_at(&pattern_point),
             PatternType::Checkers(p) => p.pattern_at(&pattern_point),
             PatternType::RadialGradient(p) => p.pattern_at(&pattern_point),
+            PatternType::Product(p) => p.pattern_at(&pattern_point),
+        }
+    }
+
+    // The color of a solid pattern, None for anything that varies over the surface.
+    pub fn solid_color(&self) -> Option<Color> {
+        match &self.pattern_type {
+            PatternType::Solid(p) => Some(p.color),
+            _ => None,
         }
     }
 
+    // A singular transform has no pattern space to map into, so the pattern is left as it was.
     pub fn set_transform(mut self, transform: Matrix) -> Self {
-        self.transform = transform;
-        self.transform_inverse = transform.inverse().unwrap();
+        if let Some(inverse) = transform.inverse() {
+            self.transform = transform;
+            self.transform_inverse = inverse;
+        }
         self
     }
 
+    pub fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
     pub fn to_pattern_space(&self, object_point: &Point) -> Point {
         self.transform_inverse * *object_point
     }
@@ -90,17 +150,33 @@ trait PatternAt {
     fn pattern_at(&self, point: &Point) -> Color;
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 enum PatternType {
+    Solid(SolidPattern),
     Stripe(StripePattern),
     Gradient(GradientPattern),
     Ring(RingPattern),
     Checkers(CheckersPattern),
     Test(TestPattern),
     RadialGradient(RadialGradientPattern),
+    Product(ProductPattern),
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
+struct SolidPattern {
+    color: Color,
+}
+
+impl PatternAt for SolidPattern {
+    fn pattern_at(&self, _point: &Point) -> Color {
+        self.color
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 struct StripePattern {
     a: Color,
     b: Color,
@@ -108,7 +184,7 @@ struct StripePattern {
 
 impl PatternAt for StripePattern {
     fn pattern_at(&self, point: &Point) -> Color {
-        if (point.x().floor() as i64 % 2) == 0 {
+        if (point.x().floor() as i64).rem_euclid(2) == 0 {
             return self.a;
         }
         self.b
@@ -116,18 +192,22 @@ impl PatternAt for StripePattern {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 struct GradientPattern {
     a: Color,
     b: Color,
+    direction: Vector,
 }
 
 impl PatternAt for GradientPattern {
     fn pattern_at(&self, point: &Point) -> Color {
-        self.a + (self.b - self.a) * point.x()
+        let fraction = (*point - Point::zero()).dot_product(&self.direction);
+        self.a + (self.b - self.a) * fraction
     }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 struct RingPattern {
     a: Color,
     b: Color,
@@ -142,14 +222,31 @@ impl PatternAt for RingPattern {
     }
 }
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 struct CheckersPattern {
     a: Color,
     b: Color,
+    mode: CheckersMode,
+}
+
+// Which coordinates the checkers alternate along; the planar modes ignore the third axis.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
+pub enum CheckersMode {
+    #[default]
+    Spatial,
+    PlanarXZ,
+    PlanarXY,
 }
 
 impl PatternAt for CheckersPattern {
     fn pattern_at(&self, point: &Point) -> Color {
-        let sum = point.x().floor() + point.y().floor() + point.z().floor();
+        let (x, y, z) = (point.x().floor(), point.y().floor(), point.z().floor());
+        let sum = match self.mode {
+            CheckersMode::Spatial => x + y + z,
+            CheckersMode::PlanarXZ => x + z,
+            CheckersMode::PlanarXY => x + y,
+        };
         if (sum % 2.0).approx_eq(0.0) {
             return self.a;
         }
@@ -158,20 +255,38 @@ impl PatternAt for CheckersPattern {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 struct RadialGradientPattern {
     a: Color,
     b: Color,
+    axis: Vector, // unit length
 }
 
 impl PatternAt for RadialGradientPattern {
     fn pattern_at(&self, point: &Point) -> Color {
         let distance = self.b - self.a;
-        let fraction = point.x().powi(2) + point.z().powi(2);
-        let fraction = fraction.sqrt() - point.y().floor();
+        let v = *point - Point::zero();
+        let height = v.dot_product(&self.axis);
+        let radius = (v - self.axis * height).magnitude();
+        let fraction = radius - height.floor();
         self.a + distance * fraction
     }
 }
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
+struct ProductPattern {
+    a: Box<Pattern>,
+    b: Box<Pattern>,
+}
+
+impl PatternAt for ProductPattern {
+    fn pattern_at(&self, point: &Point) -> Color {
+        self.a.pattern_at(point) * self.b.pattern_at(point)
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 struct TestPattern {}
 impl PatternAt for TestPattern {
     fn pattern_at(&self, point: &Point) -> Color {
@@ -218,9 +333,20 @@ mod tests {
         assert_eq!(pattern.pattern_at(&Point::new(-1.1, 0.0, 0.0)), white);
     }
 
+    #[test]
+    fn pattern_stripe_alternates_for_negative_x() {
+        let white = Color::white();
+        let black = Color::black();
+        let pattern = Pattern::new_stripe(white, black);
+        assert_eq!(pattern.pattern_at(&Point::new(-1.0, 0.0, 0.0)), black);
+        assert_eq!(pattern.pattern_at(&Point::new(-1.1, 0.0, 0.0)), white);
+        assert_eq!(pattern.pattern_at(&Point::new(-2.0, 0.0, 0.0)), white);
+        assert_eq!(pattern.pattern_at(&Point::new(-2.5, 0.0, 0.0)), black);
+    }
+
     #[test]
     fn stripe_with_object_transformation() {
-        let sphere = Object::new_sphere().set_transform(&Matrix::id().scale(2.0, 2.0, 2.0));
+        let sphere = Object::new_sphere().set_transform(Matrix::id().scale(2.0, 2.0, 2.0));
         let white = Color::new(1.0, 1.0, 1.0);
         let black = Color::new(0.0, 0.0, 0.0);
         let mut pattern = Pattern::new_stripe(white, black);
@@ -241,7 +367,7 @@ mod tests {
 
     #[test]
     fn stripe_with_both_object_and_pattern_transformation() {
-        let sphere = Object::new_sphere().set_transform(&Matrix::id().scale(2.0, 2.0, 2.0));
+        let sphere = Object::new_sphere().set_transform(Matrix::id().scale(2.0, 2.0, 2.0));
         let white = Color::new(1.0, 1.0, 1.0);
         let black = Color::new(0.0, 0.0, 0.0);
         let mut pattern = Pattern::new_stripe(white, black);
@@ -261,13 +387,26 @@ mod tests {
     fn test_pattern_set_transform() {
         let pattern = Pattern::new_test().set_transform(Matrix::id().translate(1.0, 2.0, 3.0));
         assert_eq!(pattern.transform, Matrix::id().translate(1.0, 2.0, 3.0));
+        assert_eq!(pattern.transform(), &Matrix::id().translate(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn singular_pattern_transform_is_ignored() {
+        let pattern = Pattern::new_test()
+            .set_transform(Matrix::id().translate(1.0, 2.0, 3.0))
+            .set_transform(Matrix::id().scale(0.0, 1.0, 1.0));
+        assert_eq!(pattern.transform(), &Matrix::id().translate(1.0, 2.0, 3.0));
+        assert_eq!(
+            pattern.pattern_at(&Point::new(2.0, 3.0, 4.0)),
+            Color::new(1.0, 1.0, 1.0)
+        );
     }
 
     #[test]
     fn pattern_with_object_transformation() {
         let pattern = Pattern::new_test().set_transform(Matrix::id().translate(0.5, 1.0, 1.5));
         let sphere = Object::new_sphere()
-            .set_transform(&Matrix::id().scale(2.0, 2.0, 2.0))
+            .set_transform(Matrix::id().scale(2.0, 2.0, 2.0))
             .set_material(&Material::new().with_pattern(pattern));
         let point = Point::new(2.5, 3.0, 3.5);
         let object_point = sphere.to_object_space(&point);
@@ -302,6 +441,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn gradient_along_y_is_vertical() {
+        let pattern = Pattern::new_gradient_along(
+            Color::white(),
+            Color::black(),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        assert_eq!(pattern.pattern_at(&Point::new(0.75, 0.0, 0.5)), Color::white());
+        assert_eq!(
+            pattern.pattern_at(&Point::new(0.75, 0.25, 0.5)),
+            Color::new(0.75, 0.75, 0.75)
+        );
+        assert_eq!(
+            pattern.pattern_at(&Point::new(0.0, 0.5, 0.0)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn radial_gradient_around_z_spreads_in_xy_plane() {
+        let pattern = Pattern::new_radial_gradient_around(
+            Color::white(),
+            Color::black(),
+            Vector::new(0.0, 0.0, 2.0),
+        );
+        assert_eq!(
+            pattern.pattern_at(&Point::new(0.0, 0.5, 0.0)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(
+            pattern.pattern_at(&Point::new(0.3, 0.4, 0.0)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(
+            pattern.pattern_at(&Point::new(0.3, 0.4, 0.7)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+    }
+
     #[test]
     fn ring_pattern() {
         let pattern = Pattern::new_ring(Color::new(1.0, 1.0, 1.0), Color::new(0.0, 0.0, 0.0));
@@ -323,6 +501,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn planar_checkers_ignore_the_third_axis() {
+        let (white, black) = (Color::white(), Color::black());
+        let xz = Pattern::new_checkers_with_mode(white, black, CheckersMode::PlanarXZ);
+        assert_eq!(xz.pattern_at(&Point::new(0.0, 0.99, 0.0)), white);
+        assert_eq!(xz.pattern_at(&Point::new(0.0, 1.01, 0.0)), white);
+        assert_eq!(xz.pattern_at(&Point::new(0.0, 1.01, 1.01)), black);
+        let xy = Pattern::new_checkers_with_mode(white, black, CheckersMode::PlanarXY);
+        assert_eq!(xy.pattern_at(&Point::new(0.0, 0.0, 1.01)), white);
+        assert_eq!(xy.pattern_at(&Point::new(0.0, 1.01, 0.0)), black);
+    }
+
     #[test]
     fn checkers_should_repeat_in_x() {
         let pattern = Pattern::new_checkers(Color::new(1.0, 1.0, 1.0), Color::new(0.0, 0.0, 0.0));
@@ -373,4 +563,22 @@ mod tests {
             Color::new(0.0, 0.0, 0.0)
         );
     }
+
+    #[test]
+    fn product_pattern_multiplies_children() {
+        let gray = Color::new(0.5, 0.5, 0.5);
+        let pattern = Pattern::new_product(
+            Pattern::new_gradient(Color::white(), Color::black()),
+            Pattern::new_stripe(gray, gray),
+        );
+        assert_eq!(pattern.pattern_at(&Point::new(0.0, 0.0, 0.0)), gray);
+        assert_eq!(
+            pattern.pattern_at(&Point::new(0.5, 0.0, 0.0)),
+            Color::new(0.25, 0.25, 0.25)
+        );
+        assert_eq!(
+            pattern.pattern_at(&Point::new(0.75, 0.0, 0.0)),
+            Color::new(0.125, 0.125, 0.125)
+        );
+    }
 }
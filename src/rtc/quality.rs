@@ -0,0 +1,59 @@
+use crate::rtc::camera::AntialiasMode;
+
+// Presets that set antialiasing, shadow samples and recursion depth together; apply one
+// with Camera::with_quality and World::with_quality, then override anything specific.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderQuality {
+    Draft,
+    Normal,
+    High,
+}
+
+impl RenderQuality {
+    pub fn antialiasing(&self) -> AntialiasMode {
+        match self {
+            RenderQuality::Draft => AntialiasMode::None,
+            RenderQuality::Normal => AntialiasMode::Stochastic { samples: 4, seed: None },
+            RenderQuality::High => AntialiasMode::Stochastic { samples: 16, seed: None },
+        }
+    }
+
+    // Rays per pixel the antialiasing mode traces.
+    pub fn pixel_samples(&self) -> usize {
+        match self.antialiasing() {
+            AntialiasMode::Stochastic { samples, .. } => samples,
+            _ => 1,
+        }
+    }
+
+    // Only used when the world has soft shadows turned on.
+    pub fn shadow_samples(&self) -> usize {
+        match self {
+            RenderQuality::Draft => 4,
+            RenderQuality::Normal => 16,
+            RenderQuality::High => 64,
+        }
+    }
+
+    pub fn max_depth(&self) -> u8 {
+        match self {
+            RenderQuality::Draft => 2,
+            RenderQuality::Normal => 5,
+            RenderQuality::High => 8,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draft_uses_fewer_samples_than_high() {
+        let (draft, high) = (RenderQuality::Draft, RenderQuality::High);
+        assert!(draft.pixel_samples() < RenderQuality::Normal.pixel_samples());
+        assert!(draft.pixel_samples() < high.pixel_samples());
+        assert!(draft.shadow_samples() < high.shadow_samples());
+        assert!(draft.max_depth() < high.max_depth());
+    }
+}
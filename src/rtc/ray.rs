@@ -28,9 +28,11 @@ impl Ray {
         self.refractive_indices.push(refractive_index);
     }
 
+    // Drops only the innermost matching entry; an outer object with the same index stays.
     pub fn remove_index(&mut self, refractive_index: f64){
-        self.refractive_indices.retain(|o| *o !=refractive_index);
-
+        if let Some(i) = self.refractive_indices.iter().rposition(|o| *o == refractive_index) {
+            self.refractive_indices.remove(i);
+        }
     }
 
 
@@ -42,8 +44,10 @@ impl Ray {
         self.direction
     }
 
+    // Keeps the refractive index stack so transformed rays still know what they're inside.
     pub fn transform(&self, transform: &Matrix) -> Self{
         Ray::new(*transform * self.origin, *transform * self.direction)
+            .with_indices(self.refractive_indices.clone())
     }
 }
 #[cfg(test)]
@@ -86,5 +90,13 @@ mod tests{
         assert_eq!(r2.origin, Point::new(2.0, 6.0, 12.0));
         assert_eq!(r2.direction, Vector::new(0.0, 3.0, 0.0));
     }
+
+    #[test]
+    fn transform_keeps_refractive_indices(){
+        let r = Ray::new(Point::new(1.0, 2.0, 3.0), Vector::new(0.0, 1.0, 0.0))
+            .with_indices(vec![1.0, 1.5, 2.0]);
+        let r2 = r.transform(&Matrix::id().scale(2.0, 3.0, 4.0));
+        assert_eq!(r2.get_indices(), &vec![1.0, 1.5, 2.0]);
+    }
    
 }       
@@ -1,41 +1,384 @@
 use crate::{
-    primitives::{Point, Vector},
+    float::{epsilon::EPSILON, rng::Rng},
+    primitives::{Point, Tuple, Vector},
     rtc::{
-        intersection::Intersections,
+        bounds::BoundingBox,
+        intersection::{Intersection, Intersections},
         object::Object,
         ray::Ray,
-        shapes::{plane::Plane, sphere::Sphere, cube::Cube, cone::Cone},
+        shapes::{plane::Plane, sphere::Sphere, cube::Cube, cone::Cone, disk::Disk, mesh::TriangleMesh},
+        uv,
     },
 };
 
 use super::shapes::cylinder::Cylinder;
+use std::sync::Arc;
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 pub enum Shape {
     Sphere,
-    Plane,
-    Cube,
+    Plane(bool), // true flips the normal so the front face points down
+    Cube(f64, f64, f64),
     Cylinder(f64, f64, bool),
-    Cone(f64, f64, bool)
+    Cone(f64, f64, bool),
+    Disk(f64),
+    Mesh(Arc<TriangleMesh>),
 }
 
 impl<'a> Shape {
     pub fn intersect(&self, ray: &Ray, object: &'a Object) -> Intersections<'a> {
+        self.intersect_with_epsilon(ray, object, EPSILON)
+    }
+
+    // Planes, disks, cylinders and cones use parallel_epsilon to decide when a ray runs parallel to them.
+    pub fn intersect_with_epsilon(&self, ray: &Ray, object: &'a Object, parallel_epsilon: f64) -> Intersections<'a> {
         match self {
             Shape::Sphere => Sphere::intersects(ray, object),
-            Shape::Plane => Plane::intersects(ray, object),
-            Shape::Cube => Cube::intersects(ray, object),
-            Shape::Cylinder(minimum, maximum, closed) => Cylinder::new(*minimum, *maximum, *closed).intersects(ray, object),
-            Shape::Cone(minimum, maximum, closed) => Cone::new(*minimum, *maximum, *closed).intersects(ray, object),
+            Shape::Plane(_) => Plane::intersects_with_epsilon(ray, object, parallel_epsilon),
+            Shape::Cube(x, y, z) => Cube::new(*x, *y, *z).intersects(ray, object),
+            Shape::Cylinder(minimum, maximum, closed) => Cylinder::new(*minimum, *maximum, *closed)
+                .with_parallel_epsilon(parallel_epsilon)
+                .intersects(ray, object),
+            Shape::Cone(minimum, maximum, closed) => Cone::new(*minimum, *maximum, *closed)
+                .with_parallel_epsilon(parallel_epsilon)
+                .intersects(ray, object),
+            Shape::Disk(radius) => Disk::new(*radius).with_parallel_epsilon(parallel_epsilon).intersects(ray, object),
+            Shape::Mesh(mesh) => mesh.intersects(ray, object),
+        }
+    }
+    // The closest intersection with t in [0, t_max). Shapes other than meshes have at most four
+    // roots, which are worked out on the stack without building an Intersections list.
+    pub fn nearest_intersection(
+        &self,
+        ray: &Ray,
+        object: &'a Object,
+        t_max: f64,
+        parallel_epsilon: f64,
+    ) -> Option<Intersection<'a>> {
+        if let Shape::Mesh(mesh) = self {
+            return mesh.nearest_intersection(ray, object, t_max);
+        }
+        let t = self
+            .roots(ray, parallel_epsilon)
+            .into_iter()
+            .flatten()
+            .filter(|t| (0.0..t_max).contains(t))
+            .min_by(f64::total_cmp)?;
+        let hit = Intersection::new(t, object);
+        Some(match self {
+            // the shapes whose full intersections carry uv
+            Shape::Sphere | Shape::Cube(..) => {
+                let (u, v) = self.uv_at(&ray.position(t));
+                hit.with_uv(u, v)
+            }
+            _ => hit,
+        })
+    }
+
+    // True as soon as any root lands in [0, t_max), for shadow and occlusion rays.
+    pub fn any_hit(&self, ray: &Ray, t_max: f64, parallel_epsilon: f64) -> bool {
+        match self {
+            Shape::Mesh(mesh) => mesh.any_hit(ray, t_max),
+            _ => self
+                .roots(ray, parallel_epsilon)
+                .into_iter()
+                .flatten()
+                .any(|t| (0.0..t_max).contains(&t)),
+        }
+    }
+
+    // Every root of a shape other than a mesh, unsorted; meshes are searched face by face.
+    fn roots(&self, ray: &Ray, parallel_epsilon: f64) -> [Option<f64>; 4] {
+        match self {
+            Shape::Sphere => {
+                let roots = Sphere::roots(ray);
+                [roots.map(|r| r.0), roots.map(|r| r.1), None, None]
+            }
+            Shape::Plane(_) => [Plane::root(ray, parallel_epsilon), None, None, None],
+            Shape::Cube(x, y, z) => {
+                let span = Cube::new(*x, *y, *z).slab(ray);
+                [span.map(|s| s.0), span.map(|s| s.1), None, None]
+            }
+            Shape::Cylinder(minimum, maximum, closed) => Cylinder::new(*minimum, *maximum, *closed)
+                .with_parallel_epsilon(parallel_epsilon)
+                .roots(ray),
+            Shape::Cone(minimum, maximum, closed) => Cone::new(*minimum, *maximum, *closed)
+                .with_parallel_epsilon(parallel_epsilon)
+                .roots(ray),
+            Shape::Disk(radius) => {
+                let disk = Disk::new(*radius).with_parallel_epsilon(parallel_epsilon);
+                [disk.root(ray), None, None, None]
+            }
+            Shape::Mesh(_) => [None; 4],
+        }
+    }
+
+    // Radius of a sphere around the object space origin that encloses the shape.
+    pub fn bounding_radius(&self) -> f64 {
+        match self {
+            Shape::Sphere => 1.0,
+            Shape::Plane(_) => f64::INFINITY,
+            Shape::Cube(x, y, z) => (x * x + y * y + z * z).sqrt(),
+            Shape::Cylinder(minimum, maximum, _) => {
+                let extent = minimum.abs().max(maximum.abs());
+                (1.0 + extent * extent).sqrt()
+            }
+            Shape::Cone(minimum, maximum, _) => {
+                let extent = minimum.abs().max(maximum.abs());
+                extent * 2.0_f64.sqrt()
+            }
+            Shape::Disk(radius) => *radius,
+            Shape::Mesh(mesh) => mesh.bounding_radius(),
+        }
+    }
+
+    // Object space axis-aligned box enclosing the shape.
+    pub fn bounds(&self) -> BoundingBox {
+        let symmetric = |r: f64, min_y: f64, max_y: f64| {
+            BoundingBox::new(Point::new(-r, min_y, -r), Point::new(r, max_y, r))
+        };
+        match self {
+            Shape::Sphere => symmetric(1.0, -1.0, 1.0),
+            Shape::Cube(x, y, z) => BoundingBox::new(Point::new(-x, -y, -z), Point::new(*x, *y, *z)),
+            Shape::Plane(_) => symmetric(f64::INFINITY, 0.0, 0.0),
+            Shape::Cylinder(minimum, maximum, _) => symmetric(1.0, *minimum, *maximum),
+            Shape::Cone(minimum, maximum, _) => {
+                symmetric(minimum.abs().max(maximum.abs()), *minimum, *maximum)
+            }
+            Shape::Disk(radius) => symmetric(*radius, 0.0, 0.0),
+            Shape::Mesh(mesh) => mesh.bounds(),
+        }
+    }
+
+    // Area of the shape in object space; infinite for unbounded shapes like planes.
+    pub fn surface_area(&self) -> f64 {
+        use std::f64::consts::PI;
+        match self {
+            Shape::Sphere => 4.0 * PI,
+            Shape::Plane(_) => f64::INFINITY,
+            Shape::Cube(x, y, z) => 8.0 * (x * y + y * z + z * x),
+            Shape::Cylinder(minimum, maximum, closed) => {
+                let caps = if *closed { 2.0 * PI } else { 0.0 };
+                2.0 * PI * (maximum - minimum) + caps
+            }
+            Shape::Cone(minimum, maximum, closed) => {
+                // the radius at height y is |y|, so each unit of height adds 2π|y|·√2 of slant
+                let side = if minimum * maximum >= 0.0 {
+                    (maximum * maximum - minimum * minimum).abs()
+                } else {
+                    maximum * maximum + minimum * minimum
+                };
+                let caps = if *closed {
+                    PI * (minimum * minimum + maximum * maximum)
+                } else {
+                    0.0
+                };
+                PI * 2.0_f64.sqrt() * side + caps
+            }
+            Shape::Disk(radius) => PI * radius * radius,
+            Shape::Mesh(mesh) => mesh.surface_area(),
+        }
+    }
+
+    // Only worth a bounding test when the full intersection is expensive.
+    pub fn has_expensive_intersection(&self) -> bool {
+        matches!(self, Shape::Cylinder(..) | Shape::Cone(..) | Shape::Mesh(..))
+    }
+
+    // Texture coordinates of a point on the surface, in object space.
+    pub fn uv_at(&self, object_point: &Point) -> (f64, f64) {
+        match self {
+            Shape::Plane(_) | Shape::Disk(_) => uv::planar_map(object_point, 1.0),
+            Shape::Cylinder(..) | Shape::Cone(..) => uv::cylindrical_map(object_point),
+            Shape::Cube(x, y, z) => Cube::new(*x, *y, *z).uv_at(object_point),
+            Shape::Sphere => Sphere::uv_at(object_point),
+            // meshes get projected onto a sphere around their origin
+            Shape::Mesh(_) => uv::spherical_map(object_point),
         }
     }
+
+    // A uniformly distributed point on the surface and the normal there, in object space.
+    // Unbounded shapes are sampled over their part inside -1..1 on every axis, so a plane
+    // gives the 2x2 square around its origin, which a transform can stretch to any rectangle.
+    pub fn sample_surface(&self, rng: &mut Rng) -> (Point, Vector) {
+        use std::f64::consts::PI;
+        let up = Vector::new(0.0, 1.0, 0.0);
+        match self {
+            Shape::Sphere => {
+                let z = 1.0 - 2.0 * rng.next_f64();
+                let r = (1.0 - z * z).max(0.0).sqrt();
+                let phi = 2.0 * PI * rng.next_f64();
+                let point = Point::new(r * phi.cos(), r * phi.sin(), z);
+                (point, point - Point::zero())
+            }
+            Shape::Plane(flipped) => {
+                let point = Point::new(2.0 * rng.next_f64() - 1.0, 0.0, 2.0 * rng.next_f64() - 1.0);
+                (point, if *flipped { -up } else { up })
+            }
+            Shape::Disk(radius) => {
+                let (x, z) = sample_disk(*radius, rng);
+                (Point::new(x, 0.0, z), up)
+            }
+            Shape::Cube(x, y, z) => {
+                let extents = [*x, *y, *z];
+                // each axis has two faces spanned by the other two extents
+                let axis = pick_weighted(&[y * z, x * z, x * y], rng);
+                let sign = if rng.next_f64() < 0.5 { -1.0 } else { 1.0 };
+                let mut coords = [0.0; 3];
+                let mut normal = [0.0; 3];
+                for i in 0..3 {
+                    coords[i] = if i == axis {
+                        sign * extents[i]
+                    } else {
+                        (2.0 * rng.next_f64() - 1.0) * extents[i]
+                    };
+                }
+                normal[axis] = sign;
+                (
+                    Point::new(coords[0], coords[1], coords[2]),
+                    Vector::new(normal[0], normal[1], normal[2]),
+                )
+            }
+            Shape::Cylinder(minimum, maximum, closed) => {
+                let (low, high) = clamp_unbounded(*minimum, *maximum);
+                let caps = if *closed && minimum.is_finite() && maximum.is_finite() { 1.0 } else { 0.0 };
+                match pick_weighted(&[2.0 * (high - low), caps, caps], rng) {
+                    0 => {
+                        let y = low + (high - low) * rng.next_f64();
+                        let theta = 2.0 * PI * rng.next_f64();
+                        let (x, z) = (theta.cos(), theta.sin());
+                        (Point::new(x, y, z), Vector::new(x, 0.0, z))
+                    }
+                    cap => {
+                        let (x, z) = sample_disk(1.0, rng);
+                        let (y, normal) = if cap == 1 { (low, -up) } else { (high, up) };
+                        (Point::new(x, y, z), normal)
+                    }
+                }
+            }
+            Shape::Cone(minimum, maximum, closed) => {
+                let (low, high) = clamp_unbounded(*minimum, *maximum);
+                let (low2, high2) = (low * low, high * high);
+                // the side around height y has radius |y|, so its area grows with y²
+                let side = if low * high >= 0.0 { (high2 - low2).abs() } else { high2 + low2 };
+                let closed = *closed && minimum.is_finite() && maximum.is_finite();
+                let caps = if closed { (low2, high2) } else { (0.0, 0.0) };
+                match pick_weighted(&[2.0_f64.sqrt() * side, caps.0, caps.1], rng) {
+                    0 => {
+                        let y = if low * high >= 0.0 {
+                            let sign = if low + high < 0.0 { -1.0 } else { 1.0 };
+                            sign * (low2 + (high2 - low2) * rng.next_f64()).sqrt()
+                        } else if rng.next_f64() * (low2 + high2) < high2 {
+                            high * rng.next_f64().sqrt()
+                        } else {
+                            low * rng.next_f64().sqrt()
+                        };
+                        let radius = y.abs();
+                        let theta = 2.0 * PI * rng.next_f64();
+                        let point = Point::new(radius * theta.cos(), y, radius * theta.sin());
+                        (point, self.normal_at(&point))
+                    }
+                    cap => {
+                        let y = if cap == 1 { low } else { high };
+                        let (x, z) = sample_disk(y.abs(), rng);
+                        (Point::new(x, y, z), if cap == 1 { -up } else { up })
+                    }
+                }
+            }
+            Shape::Mesh(mesh) => mesh.sample_surface(rng),
+        }
+    }
+
     pub fn normal_at(&self, object_point: &Point) -> Vector {
         match self {
             Shape::Sphere => Sphere::normal_at(object_point),
-            Shape::Plane => Plane::normal_at(object_point),
-            Shape::Cube => Cube::normal_at(object_point),
+            Shape::Plane(false) => Plane::normal_at(object_point),
+            Shape::Plane(true) => -Plane::normal_at(object_point),
+            Shape::Cube(x, y, z) => Cube::new(*x, *y, *z).normal_at(object_point),
             Shape::Cylinder(minimum, maximum, closed) => Cylinder::new(*minimum, *maximum, *closed).normal_at(object_point),
             Shape::Cone(minimum, maximum, closed) => Cone::new(*minimum, *maximum, *closed).normal_at(object_point),
+            Shape::Disk(radius) => Disk::new(*radius).normal_at(object_point),
+            Shape::Mesh(mesh) => mesh.normal_at(object_point),
+        }
+    }
+}
+
+// Uniform over a disk of the given radius in the xz plane.
+fn sample_disk(radius: f64, rng: &mut Rng) -> (f64, f64) {
+    let r = radius * rng.next_f64().sqrt();
+    let theta = 2.0 * std::f64::consts::PI * rng.next_f64();
+    (r * theta.cos(), r * theta.sin())
+}
+
+// Infinite ends of a cylinder or cone are cut off at -1 and 1.
+fn clamp_unbounded(minimum: f64, maximum: f64) -> (f64, f64) {
+    let finite_or = |v: f64, fallback: f64| if v.is_finite() { v } else { fallback };
+    (finite_or(minimum, -1.0), finite_or(maximum, 1.0))
+}
+
+// Index chosen with probability proportional to its weight.
+fn pick_weighted(weights: &[f64], rng: &mut Rng) -> usize {
+    let mut target = rng.next_f64() * weights.iter().sum::<f64>();
+    for (i, weight) in weights.iter().enumerate() {
+        if target < *weight {
+            return i;
         }
+        target -= weight;
+    }
+    weights.iter().rposition(|w| *w > 0.0).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::float::ApproxEq;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn unit_shape_surface_areas() {
+        assert!(Shape::Sphere.surface_area().approx_eq(4.0 * PI));
+        assert_eq!(Shape::Cube(1.0, 1.0, 1.0).surface_area(), 24.0);
+        assert!(Shape::Plane(false).surface_area().is_infinite());
+        assert!(Shape::Cylinder(0.0, 1.0, true).surface_area().approx_eq(4.0 * PI));
+        assert!(Shape::Cone(-1.0, 1.0, false)
+            .surface_area()
+            .approx_eq(2.0 * PI * 2.0_f64.sqrt()));
+    }
+
+    #[test]
+    fn surface_samples_lie_on_the_surface() {
+        let mut rng = Rng::new(7);
+        let shapes = [
+            Shape::Cube(1.0, 2.0, 0.5),
+            Shape::Cylinder(-1.0, 2.0, true),
+            Shape::Cone(-1.0, 0.5, true),
+            Shape::Disk(2.0),
+        ];
+        for shape in shapes {
+            for _ in 0..200 {
+                let (point, normal) = shape.sample_surface(&mut rng);
+                let (min, max) = (shape.bounds().min(), shape.bounds().max());
+                let inside = |v: f64, lo: f64, hi: f64| v >= lo - EPSILON && v <= hi + EPSILON;
+                assert!(
+                    inside(point.x(), min.x(), max.x())
+                        && inside(point.y(), min.y(), max.y())
+                        && inside(point.z(), min.z(), max.z()),
+                    "{shape:?} sampled {point:?}"
+                );
+                assert_eq!(normal.normalize(), shape.normal_at(&point).normalize());
+            }
+        }
+        let (point, normal) = Shape::Plane(true).sample_surface(&mut rng);
+        assert!(point.x().abs() <= 1.0 && point.y() == 0.0 && point.z().abs() <= 1.0);
+        assert_eq!(normal, Vector::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn unit_cube_bounds() {
+        let bounds = Shape::Cube(1.0, 1.0, 1.0).bounds();
+        assert_eq!(bounds.min(), Point::new(-1.0, -1.0, -1.0));
+        assert_eq!(bounds.max(), Point::new(1.0, 1.0, 1.0));
+        assert_eq!(bounds.volume(), 8.0);
     }
 }
@@ -1,8 +1,11 @@
 use crate::{
-    float::{approx_eq::ApproxEq, epsilon::LOW_EPSILON},
+    float::{
+        approx_eq::ApproxEq,
+        epsilon::{EPSILON, LOW_EPSILON},
+    },
     primitives::{Point, Tuple, Vector},
     rtc::{
-        intersection::{Intersection, Intersections},
+        intersection::Intersections,
         object::Object,
         ray::Ray,
     },
@@ -12,6 +15,7 @@ pub struct Cone {
     minimum: f64,
     maximum: f64,
     closed: bool,
+    parallel_epsilon: f64,
 }
 
 impl Default for Cone {
@@ -20,6 +24,7 @@ impl Default for Cone {
             closed: false,
             minimum: f64::NEG_INFINITY,
             maximum: f64::INFINITY,
+            parallel_epsilon: EPSILON,
         }
     }
 }
@@ -29,15 +34,31 @@ impl<'a> Cone {
             minimum,
             maximum,
             closed,
+            parallel_epsilon: EPSILON,
         }
     }
 
+    pub fn with_parallel_epsilon(mut self, parallel_epsilon: f64) -> Self {
+        self.parallel_epsilon = parallel_epsilon;
+        self
+    }
+
     pub fn check_cap(&self, ray: &Ray, t: f64, y: f64) -> bool {
         let x = ray.origin().x() + t * ray.direction().x();
         let z = ray.origin().z() + t * ray.direction().z();
-        (x.powi(2) + z.powi(2)) <= y.abs() 
+        (x.powi(2) + z.powi(2)) <= y.powi(2)
     }
     pub fn intersects(&self, ray: &Ray, object: &'a Object) -> Intersections<'a> {
+        let mut xs = Intersections::new();
+        for t in self.roots(ray).into_iter().flatten() {
+            xs.push(object, t);
+        }
+        xs
+    }
+
+    // The side roots within the height range, then the two cap roots.
+    pub fn roots(&self, ray: &Ray) -> [Option<f64>; 4] {
+        let [bottom, top] = self.cap_roots(ray);
         let a =
             ray.direction().x().powi(2) - ray.direction().y().powi(2) + ray.direction().z().powi(2);
         let b = 2.0 * ray.origin().x() * ray.direction().x()
@@ -46,66 +67,63 @@ impl<'a> Cone {
         let c =
             ray.origin().x().powi(2) - ray.origin().y().powi(2) + ray.origin().z().powi(2) ;
 
-        if a.approx_eq(0.0) && b.approx_eq(0.0) {
+        let a_zero = a.approx_eq_epsilon(0.0, self.parallel_epsilon);
+        let b_zero = b.approx_eq_epsilon(0.0, self.parallel_epsilon);
+        if a_zero && b_zero {
             // ray is parallel to the cone surface
-            return self.intersection_at_caps(ray, object);
+            return [None, None, bottom, top];
         }
-        if a.approx_eq(0.0) && !b.approx_eq(0.0) {
+        if a_zero {
             //ray intersects the cone at a single point
             let t = -c / (2.0 * b);
-            let mut xs = Intersections::new().with_intersections(vec![Intersection::new(t, object)]);
-            xs.extend(self.intersection_at_caps(ray, object));
-            return xs;
+            return [Some(t), None, bottom, top];
         }
         let discriminant = b.powi(2) - 4.0 * a * c;
         if discriminant < 0.0 {
-            return Intersections::new();
+            return [None; 4];
         }
 
         let t0 = (-b - discriminant.sqrt()) / (2.0 * a);
         let t1 = (-b + discriminant.sqrt()) / (2.0 * a);
 
         let (t0, t1) = if t0 > t1 { (t1, t0) } else { (t0, t1) };
-        let mut xs = Intersections::new();
-        let y0 = ray.origin().y() + t0 * ray.direction().y();
-        if self.minimum < y0 && y0 < self.maximum {
-            xs.push(object, t0);
-        }
-
-        let y1 = ray.origin().y() + t1 * ray.direction().y();
-        if self.minimum < y1 && y1 < self.maximum {
-            xs.push(object, t1);
-        }
-        let intersection_at_caps = self.intersection_at_caps(ray, object);
-        xs.extend(intersection_at_caps);
-        xs
+        let within = |t: f64| {
+            let y = ray.origin().y() + t * ray.direction().y();
+            (self.minimum < y && y < self.maximum).then_some(t)
+        };
+        [within(t0), within(t1), bottom, top]
     }
 
-    fn intersection_at_caps(&self, ray: &Ray, object: &'a Object) -> Intersections<'a> {
-        let mut xs = Intersections::new();
-        if !self.closed || ray.direction().y().approx_eq(0.0) {
-            return xs;
+    fn cap_roots(&self, ray: &Ray) -> [Option<f64>; 2] {
+        if !self.closed || ray.direction().y().approx_eq_epsilon(0.0, self.parallel_epsilon) {
+            return [None, None];
         }
         let t0 = (self.minimum - ray.origin().y()) / ray.direction().y();
-        if self.check_cap(ray, t0, self.minimum) {
-            xs.push(object, t0);
-        }
         let t1 = (self.maximum - ray.origin().y()) / ray.direction().y();
-        if self.check_cap(ray, t1, self.maximum) {
-            xs.push(object, t1);
-        }
-        xs
+        [
+            self.check_cap(ray, t0, self.minimum).then_some(t0),
+            self.check_cap(ray, t1, self.maximum).then_some(t1),
+        ]
     }
 
     pub fn normal_at(&self, object_point: &Point) -> Vector {
+        // a cap's radius is the height it sits at
         let dist = object_point.x().powi(2) + object_point.z().powi(2);
-        if dist < 1.0 && object_point.y() >= self.maximum - LOW_EPSILON {
+        if dist < self.maximum.powi(2) && object_point.y() >= self.maximum - LOW_EPSILON {
             return Vector::new(0.0, 1.0, 0.0);
-        } else if dist < 1.0 && object_point.y() <= self.minimum + LOW_EPSILON {
+        } else if dist < self.minimum.powi(2) && object_point.y() <= self.minimum + LOW_EPSILON {
             return Vector::new(0.0, -1.0, 0.0);
         }
-        let y = (object_point.x().powi(2) + object_point.z().powi(2)).sqrt();
-        let y = if object_point.y() > 0.0 { -y } else { y };
+        // y > 0 is the upper nappe, whose normals tilt down; y <= 0 (including y == 0 exactly)
+        // is the lower one, tilting up. Either way the slope is 45°, so the direction only
+        // depends on the angle around the axis and not on how close the point is to the apex.
+        let upper = object_point.y() > 0.0;
+        let radius = dist.sqrt();
+        if radius == 0.0 {
+            // on the axis there is no angle to go by; use the nappe's average normal
+            return Vector::new(0.0, if upper { -1.0 } else { 1.0 }, 0.0);
+        }
+        let y = if upper { -radius } else { radius };
         Vector::new(object_point.x(), y, object_point.z())
     }
 }
@@ -176,7 +194,7 @@ mod tests {
     fn computing_normal_vector_on_cone(){
         let c = Object::new_cone(f64::NEG_INFINITY, f64::INFINITY);
         let normals = vec![
-            (Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 0.0)),
+            (Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0)),
             (Point::new(1.0, 1.0, 1.0), Vector::new(1.0, -2.0_f64.sqrt(), 1.0)),
             (Point::new(-1.0, -1.0, 0.0), Vector::new(-1.0, 1.0, 0.0)),
         ];
@@ -185,4 +203,49 @@ mod tests {
             assert_eq!(n, normal);
         }
     }
+
+    #[test]
+    fn cone_normals_keep_direction_toward_apex_on_both_nappes() {
+        let c = Cone::default();
+        for angle in [0.0, 1.0, 2.5, 4.0, 5.5_f64] {
+            let (s, co) = angle.sin_cos();
+            let upper = Vector::new(co, -1.0, s).normalize();
+            let lower = Vector::new(co, 1.0, s).normalize();
+            for h in [2.0, 1.0, 1e-3, 1e-9] {
+                let above = c.normal_at(&Point::new(h * co, h, h * s)).normalize();
+                let below = c.normal_at(&Point::new(h * co, -h, h * s)).normalize();
+                assert_eq!(above, upper);
+                assert_eq!(below, lower);
+            }
+            // exactly at y == 0 but off the axis counts as the lower nappe
+            let level = c.normal_at(&Point::new(1e-6 * co, 0.0, 1e-6 * s)).normalize();
+            assert_eq!(level, lower);
+        }
+    }
+
+    #[test]
+    fn cone_normal_at_apex_is_usable() {
+        let c = Object::new_cone(-1.0, 1.0);
+        let n = c.normal_at(&Point::zero());
+        assert!(n.x().is_finite() && n.y().is_finite() && n.z().is_finite());
+        assert_eq!(n, Vector::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn closed_cone_cap_normals_use_cap_radius() {
+        let c = Cone::new(-0.5, 0.5, true);
+        assert_eq!(c.normal_at(&Point::new(0.2, 0.5, 0.2)), Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(c.normal_at(&Point::new(0.2, -0.5, 0.0)), Vector::new(0.0, -1.0, 0.0));
+        // just below the rim on the side, not on the cap
+        let side = c.normal_at(&Point::new(0.5, 0.5 - 1e-3, 0.0)).normalize();
+        assert_eq!(side, Vector::new(1.0, -1.0, 0.0).normalize());
+    }
+
+    #[test]
+    fn grazing_cap_ray_respects_parallel_epsilon() {
+        let ray = Ray::new(Point::new(0.0, 1.0 + 1e-4, -0.5), Vector::new(0.0, -1e-4, 1.0));
+        let top_cap = |c: Cone| c.roots(&ray)[3];
+        assert!(top_cap(Cone::new(-1.0, 1.0, true)).unwrap().approx_eq(1.0));
+        assert_eq!(top_cap(Cone::new(-1.0, 1.0, true).with_parallel_epsilon(1e-3)), None);
+    }
 }
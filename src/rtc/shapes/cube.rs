@@ -1,13 +1,28 @@
 use crate::{rtc::{intersection::{Intersections, Intersection}, object::Object, ray::Ray}, primitives::{Vector, Point}};
 use crate::primitives::Tuple;
+use crate::rtc::uv::cube_map;
+// Axis-aligned box centered on the origin, spanning [-half, half] on each axis.
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Cube{
+    half_x: f64,
+    half_y: f64,
+    half_z: f64,
+}
+
+impl Default for Cube {
+    fn default() -> Self {
+        Cube::new(1.0, 1.0, 1.0)
+    }
 }
 
 impl<'a> Cube{
-    fn check_axis(origin: f64, direction: f64) -> (f64, f64){
-        let tmin_numerator = -1.0 - origin;
-        let tmax_numerator = 1.0 - origin;
+    pub fn new(half_x: f64, half_y: f64, half_z: f64) -> Self {
+        Cube { half_x, half_y, half_z }
+    }
+
+    fn check_axis(origin: f64, direction: f64, half: f64) -> (f64, f64){
+        let tmin_numerator = -half - origin;
+        let tmax_numerator = half - origin;
         let (tmin, tmax) = if direction.abs() >= 1e-5{
             (tmin_numerator / direction, tmax_numerator / direction)
         }else{
@@ -19,30 +34,49 @@ impl<'a> Cube{
             (tmin, tmax)
         }
     }
-    pub fn intersects(ray: &Ray, object: &'a Object) -> Intersections<'a> {
-        let ray = ray.transform(&object.transform_inverse());
-        let (xtmin, xtmax) = Self::check_axis(ray.origin().x(), ray.direction().x());
-        let (ytmin, ytmax) = Self::check_axis(ray.origin().y(), ray.direction().y());
-        let (ztmin, ztmax) = Self::check_axis(ray.origin().z(), ray.direction().z());
+    pub fn intersects(&self, ray: &Ray, object: &'a Object) -> Intersections<'a> {
+        let (tmin, tmax) = match self.slab(ray) {
+            Some(span) => span,
+            None => return Intersections::new(),
+        };
+        let hit = |t: f64| {
+            let (u, v) = self.uv_at(&ray.position(t));
+            Intersection::new(t, object).with_uv(u, v)
+        };
+        Intersections::new().with_intersections(vec![hit(tmin), hit(tmax)])
+    }
+
+    // Where the ray enters and leaves the box, or None when it misses.
+    pub fn slab(&self, ray: &Ray) -> Option<(f64, f64)> {
+        let (xtmin, xtmax) = Self::check_axis(ray.origin().x(), ray.direction().x(), self.half_x);
+        let (ytmin, ytmax) = Self::check_axis(ray.origin().y(), ray.direction().y(), self.half_y);
+        let (ztmin, ztmax) = Self::check_axis(ray.origin().z(), ray.direction().z(), self.half_z);
 
         let tmin = xtmin.max(ytmin).max(ztmin);
         let tmax = xtmax.min(ytmax).min(ztmax);
+        (tmin <= tmax).then_some((tmin, tmax))
+    }
 
-        if tmin > tmax {
-            return Intersections::new();
-        }
-
-        Intersections::new().with_intersections(vec![Intersection::new(tmin, object),Intersection::new(tmax, object)])
+    // Face-local coordinates from cube_map, after stretching the box back to a unit cube.
+    pub fn uv_at(&self, point: &Point) -> (f64, f64) {
+        let unit = Point::new(point.x() / self.half_x, point.y() / self.half_y, point.z() / self.half_z);
+        let (_, u, v) = cube_map(&unit);
+        (u, v)
     }
 
-    pub fn normal_at(point: &Point) -> Vector{
-        let maxc = point.x().abs().max(point.y().abs()).max(point.z().abs());
-        if maxc == point.x().abs(){
-            Vector::new(point.x(), 0.0, 0.0)
-        }else if maxc == point.y().abs(){
-            Vector::new(0.0, point.y(), 0.0)
+    // The face is on the axis where the point is relatively closest to the extent.
+    pub fn normal_at(&self, point: &Point) -> Vector{
+        let distance = (*point - Point::zero()).abs();
+        let x = distance.x() / self.half_x;
+        let y = distance.y() / self.half_y;
+        let z = distance.z() / self.half_z;
+        let maxc = x.max(y).max(z);
+        if maxc == x{
+            Vector::new(point.x().signum(), 0.0, 0.0)
+        }else if maxc == y{
+            Vector::new(0.0, point.y().signum(), 0.0)
         }else{
-            Vector::new(0.0, 0.0, point.z())
+            Vector::new(0.0, 0.0, point.z().signum())
         }
     }
 }
@@ -66,7 +100,7 @@ mod tests{
         let c = Object::new_cube();
         for (origin, direction, t1, t2) in intersections{
             let r = Ray::new(origin, direction);
-            let xs = Cube::intersects(&r, &c);
+            let xs = Cube::default().intersects(&r, &c);
             assert_eq!(xs.count(), 2);
             assert_eq!(xs[0].t(), t1);
             assert_eq!(xs[1].t(), t2);
@@ -86,7 +120,7 @@ mod tests{
         ];
         for (origin, direction) in intersections{
             let r = Ray::new(origin, direction);
-            let xs = Cube::intersects(&r, &c);
+            let xs = Cube::default().intersects(&r, &c);
             assert_eq!(xs.count(), 0);
         }
     }
@@ -104,8 +138,20 @@ mod tests{
             (Point::new(-1.0, -1.0, -1.0), Vector::new(-1.0, 0.0, 0.0)),
         ];
         for (point, normal) in normals{
-            let n = Cube::normal_at(&point);
+            let n = Cube::default().normal_at(&point);
             assert_eq!(n, normal);
         }
     }
+
+    #[test]
+    fn box_with_half_size_two(){
+        let b = Object::new_box(2.0, 2.0, 2.0);
+        let r = Ray::new(Point::new(5.0, 0.5, 0.0), Vector::new(-1.0, 0.0, 0.0));
+        let xs = b.intersect(&r);
+        assert_eq!(xs.count(), 2);
+        assert_eq!(r.position(xs[0].t()), Point::new(2.0, 0.5, 0.0));
+        assert_eq!(b.normal_at(&Point::new(2.0, 0.5, 0.0)), Vector::new(1.0, 0.0, 0.0));
+        let flat = Object::new_box(4.0, 1.0, 4.0);
+        assert_eq!(flat.normal_at(&Point::new(2.0, 1.0, -3.0)), Vector::new(0.0, 1.0, 0.0));
+    }
 }
@@ -1,4 +1,4 @@
-use crate::float::epsilon::LOW_EPSILON;
+use crate::float::epsilon::{EPSILON, LOW_EPSILON};
 use crate::float::ApproxEq;
 use crate::primitives::{Point, Tuple, Vector};
 use crate::rtc::{intersection::Intersections, object::Object, ray::Ray};
@@ -7,6 +7,7 @@ pub struct Cylinder {
     minimum: f64,
     maximum: f64,
     closed: bool,
+    parallel_epsilon: f64,
 }
 
 impl Default for Cylinder {
@@ -15,13 +16,19 @@ impl Default for Cylinder {
             closed: false,
             minimum: f64::NEG_INFINITY,
             maximum: f64::INFINITY,
+            parallel_epsilon: EPSILON,
         }
     }
 }
 
 impl<'a> Cylinder {
     pub fn new(minimum: f64, maximum: f64, closed: bool) -> Self {
-        Cylinder { minimum, maximum, closed}
+        Cylinder { minimum, maximum, closed, parallel_epsilon: EPSILON }
+    }
+
+    pub fn with_parallel_epsilon(mut self, parallel_epsilon: f64) -> Self {
+        self.parallel_epsilon = parallel_epsilon;
+        self
     }
 
     pub fn check_cap(&self, ray: &Ray, t: f64) -> bool {
@@ -30,52 +37,50 @@ impl<'a> Cylinder {
         (x.powi(2) + z.powi(2)) <= 1.0
     }
     pub fn intersects(&self, ray: &Ray, object: &'a Object) -> Intersections<'a> {
+        let mut xs = Intersections::new();
+        for t in self.roots(ray).into_iter().flatten() {
+            xs.push(object, t);
+        }
+        xs
+    }
+
+    // The two side roots within the height range, then the two cap roots.
+    pub fn roots(&self, ray: &Ray) -> [Option<f64>; 4] {
+        let [bottom, top] = self.cap_roots(ray);
         let a = ray.direction().x().powi(2) + ray.direction().z().powi(2);
-        if a.approx_eq(0.0) {
+        if a.approx_eq_epsilon(0.0, self.parallel_epsilon) {
             // ray is parallel to the y axis
-            return self.intersection_at_caps(ray, object);
+            return [None, None, bottom, top];
         }
         let b = 2.0 * ray.origin().x() * ray.direction().x()
             + 2.0 * ray.origin().z() * ray.direction().z();
         let c = ray.origin().x().powi(2) + ray.origin().z().powi(2) - 1.0;
         let discriminant = b.powi(2) - 4.0 * a * c;
         if discriminant < 0.0 {
-            return Intersections::new();
+            return [None; 4];
         }
 
         let t0 = (-b - discriminant.sqrt()) / (2.0 * a);
         let t1 = (-b + discriminant.sqrt()) / (2.0 * a);
 
         let (t0, t1) = if t0 > t1 { (t1, t0) } else { (t0, t1) };
-        let mut xs = Intersections::new();
-        let y0 = ray.origin().y() + t0 * ray.direction().y();
-        if self.minimum < y0 && y0 < self.maximum {
-            xs.push(object, t0);
-        }
-
-        let y1 = ray.origin().y() + t1 * ray.direction().y();
-        if self.minimum < y1 && y1 < self.maximum {
-            xs.push(object, t1);
-        }
-        let intersection_at_caps = self.intersection_at_caps(ray, object);
-        xs.extend(intersection_at_caps);
-        xs
+        let within = |t: f64| {
+            let y = ray.origin().y() + t * ray.direction().y();
+            (self.minimum < y && y < self.maximum).then_some(t)
+        };
+        [within(t0), within(t1), bottom, top]
     }
 
-    fn intersection_at_caps(&self, ray: &Ray, object: &'a Object) -> Intersections<'a> {
-        let mut xs = Intersections::new();
-        if !self.closed || ray.direction().y().approx_eq(0.0) {
-            return xs;
+    fn cap_roots(&self, ray: &Ray) -> [Option<f64>; 2] {
+        if !self.closed || ray.direction().y().approx_eq_epsilon(0.0, self.parallel_epsilon) {
+            return [None, None];
         }
         let t0 = (self.minimum - ray.origin().y()) / ray.direction().y();
-        if self.check_cap(ray, t0) {
-            xs.push(object, t0);
-        }
         let t1 = (self.maximum - ray.origin().y()) / ray.direction().y();
-        if self.check_cap(ray, t1) {
-            xs.push(object, t1);
-        }
-        xs
+        [
+            self.check_cap(ray, t0).then_some(t0),
+            self.check_cap(ray, t1).then_some(t1),
+        ]
     }
 
     pub fn normal_at(&self, object_point: &Point) -> Vector {
@@ -0,0 +1,82 @@
+use crate::{
+    float::epsilon,
+    primitives::{Point, Tuple, Vector},
+    rtc::intersection::Intersections,
+    rtc::object::Object,
+    rtc::ray::Ray,
+};
+
+// A plane clipped to a circle of the given radius around the y axis.
+pub struct Disk {
+    radius: f64,
+    parallel_epsilon: f64,
+}
+
+impl<'a> Disk {
+    pub fn new(radius: f64) -> Self {
+        Disk { radius, parallel_epsilon: epsilon::EPSILON }
+    }
+
+    pub fn with_parallel_epsilon(mut self, parallel_epsilon: f64) -> Self {
+        self.parallel_epsilon = parallel_epsilon;
+        self
+    }
+
+    pub fn normal_at(&self, _point: &Point) -> Vector {
+        Vector::new(0.0, 1.0, 0.0)
+    }
+
+    pub fn intersects(&self, ray: &Ray, object: &'a Object) -> Intersections<'a> {
+        let mut intersections = Intersections::new();
+        if let Some(t) = self.root(ray) {
+            intersections.push(object, t);
+        }
+        intersections
+    }
+
+    pub fn root(&self, ray: &Ray) -> Option<f64> {
+        if ray.direction().y().abs() < self.parallel_epsilon {
+            return None;
+        }
+        let t = -ray.origin().y() / ray.direction().y();
+        let hit = ray.position(t);
+        (hit.x().powi(2) + hit.z().powi(2) <= self.radius.powi(2)).then_some(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ray_through_center_hits() {
+        let disk = Object::new_disk(2.0);
+        let ray = Ray::new(Point::new(0.0, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let xs = Disk::new(2.0).intersects(&ray, &disk);
+        assert_eq!(xs.count(), 1);
+        assert_eq!(xs[0].t(), 1.0);
+    }
+
+    #[test]
+    fn ray_beyond_radius_misses() {
+        let disk = Object::new_disk(2.0);
+        let inside = Ray::new(Point::new(1.9, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let outside = Ray::new(Point::new(1.5, 1.0, 1.5), Vector::new(0.0, -1.0, 0.0));
+        assert_eq!(disk.intersect(&inside).count(), 1);
+        assert_eq!(disk.intersect(&outside).count(), 0);
+    }
+
+    #[test]
+    fn normal_is_constant_on_a_disk() {
+        let disk = Disk::new(1.0);
+        assert_eq!(disk.normal_at(&Point::new(0.0, 0.0, 0.0)), Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(disk.normal_at(&Point::new(0.5, 0.0, -0.5)), Vector::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn grazing_ray_respects_parallel_epsilon() {
+        let ray = Ray::new(Point::new(0.0, 1e-4, -1.0), Vector::new(0.0, -1e-4, 1.0));
+        assert_eq!(Disk::new(1.0).root(&ray), Some(1.0));
+        assert_eq!(Disk::new(1.0).with_parallel_epsilon(1e-3).root(&ray), None);
+    }
+}
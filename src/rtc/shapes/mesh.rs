@@ -0,0 +1,266 @@
+use crate::{
+    float::{epsilon::EPSILON, rng::Rng},
+    primitives::{Point, Tuple, Vector},
+    rtc::bounds::BoundingBox,
+    rtc::intersection::{Intersection, Intersections},
+    rtc::object::Object,
+    rtc::ray::Ray,
+};
+
+// Flat-shaded triangles sharing one vertex buffer; faces index into `vertices`.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
+pub struct TriangleMesh {
+    vertices: Vec<Point>,
+    faces: Vec<[usize; 3]>,
+    normals: Vec<Vector>, // one per face, cached
+}
+
+impl<'a> TriangleMesh {
+    pub fn new(vertices: Vec<Point>, faces: Vec<[usize; 3]>) -> Self {
+        assert!(
+            faces.iter().flatten().all(|&i| i < vertices.len()),
+            "Mesh face refers to a missing vertex"
+        );
+        let normals = faces
+            .iter()
+            .map(|&[a, b, c]| {
+                let e1 = vertices[b] - vertices[a];
+                let e2 = vertices[c] - vertices[a];
+                e2.cross_product(e1).normalize()
+            })
+            .collect();
+        TriangleMesh {
+            vertices,
+            faces,
+            normals,
+        }
+    }
+
+    pub fn vertices(&self) -> &[Point] {
+        &self.vertices
+    }
+
+    pub fn faces(&self) -> &[[usize; 3]] {
+        &self.faces
+    }
+
+    pub fn intersects(&self, ray: &Ray, object: &'a Object) -> Intersections<'a> {
+        let mut intersections = Intersections::new();
+        for face in 0..self.faces.len() {
+            if let Some((t, u, v)) = self.intersect_face(ray, face) {
+                intersections.push_with_uv(object, t, u, v);
+            }
+        }
+        intersections
+    }
+
+    // Keeps only the closest face hit in [0, t_max) instead of collecting every face's.
+    pub fn nearest_intersection(&self, ray: &Ray, object: &'a Object, t_max: f64) -> Option<Intersection<'a>> {
+        let mut nearest: Option<(f64, f64, f64)> = None;
+        for face in 0..self.faces.len() {
+            if let Some((t, u, v)) = self.intersect_face(ray, face) {
+                let limit = nearest.map_or(t_max, |(t, _, _)| t);
+                if t >= 0.0 && t < limit {
+                    nearest = Some((t, u, v));
+                }
+            }
+        }
+        nearest.map(|(t, u, v)| Intersection::new(t, object).with_uv(u, v))
+    }
+
+    // Stops at the first face hit in [0, t_max).
+    pub fn any_hit(&self, ray: &Ray, t_max: f64) -> bool {
+        (0..self.faces.len()).any(|face| {
+            self.intersect_face(ray, face)
+                .is_some_and(|(t, _, _)| t >= 0.0 && t < t_max)
+        })
+    }
+
+    // Intersections don't record which face was hit, so look for the face the point lies on.
+    // A mesh with no faces can't be hit, but still answers with the same normal as its samples.
+    pub fn normal_at(&self, point: &Point) -> Vector {
+        (0..self.faces.len())
+            .filter(|&face| self.contains(face, point))
+            .min_by(|&a, &b| {
+                self.plane_distance(a, point)
+                    .total_cmp(&self.plane_distance(b, point))
+            })
+            .map(|face| self.normals[face])
+            .or_else(|| self.normals.first().copied())
+            .unwrap_or(Vector::new(0.0, 1.0, 0.0))
+    }
+
+    // Picks a face with probability proportional to its area, then a uniform point on it.
+    // A mesh with no faces has no surface, so like bounds it collapses to the origin.
+    pub fn sample_surface(&self, rng: &mut Rng) -> (Point, Vector) {
+        if self.faces.is_empty() {
+            return (Point::zero(), Vector::new(0.0, 1.0, 0.0));
+        }
+        let areas: Vec<f64> = self
+            .faces
+            .iter()
+            .map(|&[a, b, c]| {
+                let e1 = self.vertices[b] - self.vertices[a];
+                let e2 = self.vertices[c] - self.vertices[a];
+                e1.cross_product(e2).magnitude() / 2.0
+            })
+            .collect();
+        let mut target = rng.next_f64() * areas.iter().sum::<f64>();
+        let face = areas
+            .iter()
+            .position(|area| {
+                target -= area;
+                target < 0.0
+            })
+            .unwrap_or_else(|| self.faces.len() - 1);
+        let [a, b, c] = self.faces[face];
+        let s = rng.next_f64().sqrt();
+        let t = rng.next_f64();
+        let p = self.vertices[a]
+            + (self.vertices[b] - self.vertices[a]) * (s * (1.0 - t))
+            + (self.vertices[c] - self.vertices[a]) * (s * t);
+        (p, self.normals[face])
+    }
+
+    pub fn bounding_radius(&self) -> f64 {
+        self.vertices
+            .iter()
+            .map(|v| (*v - Point::zero()).magnitude())
+            .fold(0.0, f64::max)
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        self.faces
+            .iter()
+            .map(|&[a, b, c]| {
+                let e1 = self.vertices[b] - self.vertices[a];
+                let e2 = self.vertices[c] - self.vertices[a];
+                e1.cross_product(e2).magnitude() / 2.0
+            })
+            .sum()
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        let corner = |p: &Point| BoundingBox::new(*p, *p);
+        self.vertices
+            .iter()
+            .map(corner)
+            .reduce(|a, b| a.merge(&b))
+            .unwrap_or_else(|| corner(&Point::zero()))
+    }
+
+    // Möller–Trumbore; returns t and the barycentric (u, v) of the hit.
+    fn intersect_face(&self, ray: &Ray, face: usize) -> Option<(f64, f64, f64)> {
+        let [a, b, c] = self.faces[face];
+        let (p1, p2, p3) = (self.vertices[a], self.vertices[b], self.vertices[c]);
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+        let dir_cross_e2 = ray.direction().cross_product(e2);
+        let det = e1.dot_product(&dir_cross_e2);
+        if det.abs() < EPSILON {
+            return None;
+        }
+        let f = 1.0 / det;
+        let p1_to_origin = ray.origin() - p1;
+        let u = f * p1_to_origin.dot_product(&dir_cross_e2);
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let origin_cross_e1 = p1_to_origin.cross_product(e1);
+        let v = f * ray.direction().dot_product(&origin_cross_e1);
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        Some((f * e2.dot_product(&origin_cross_e1), u, v))
+    }
+
+    fn plane_distance(&self, face: usize, point: &Point) -> f64 {
+        let p1 = self.vertices[self.faces[face][0]];
+        (*point - p1).dot_product(&self.normals[face]).abs()
+    }
+
+    fn contains(&self, face: usize, point: &Point) -> bool {
+        let [a, b, c] = self.faces[face];
+        let normal = self.normals[face];
+        let corners = [self.vertices[a], self.vertices[b], self.vertices[c]];
+        // the point is inside when it is on the same side of all three edges
+        let signs: Vec<f64> = (0..3)
+            .map(|i| {
+                let edge = corners[(i + 1) % 3] - corners[i];
+                let to_point = *point - corners[i];
+                to_point.cross_product(edge).dot_product(&normal)
+            })
+            .collect();
+        signs.iter().all(|s| *s >= -EPSILON) || signs.iter().all(|s| *s <= EPSILON)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad() -> Object {
+        let vertices = vec![
+            Point::new(-1.0, 0.0, -1.0),
+            Point::new(1.0, 0.0, -1.0),
+            Point::new(1.0, 0.0, 1.0),
+            Point::new(-1.0, 0.0, 1.0),
+        ];
+        Object::new_mesh(vertices, vec![[0, 1, 2], [0, 2, 3]])
+    }
+
+    #[test]
+    fn ray_hits_each_triangle_of_quad() {
+        let quad = quad();
+        // below the diagonal is the first triangle, above it the second
+        for (x, z) in [(0.5, -0.5), (-0.5, 0.5)] {
+            let ray = Ray::new(Point::new(x, 1.0, z), Vector::new(0.0, -1.0, 0.0));
+            let xs = quad.intersect(&ray);
+            assert_eq!(xs.count(), 1);
+            assert_eq!(xs[0].t(), 1.0);
+            assert_eq!(quad.normal_at(&ray.position(1.0)).y().abs(), 1.0);
+        }
+        let miss = Ray::new(Point::new(1.5, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        assert_eq!(quad.intersect(&miss).count(), 0);
+    }
+
+    #[test]
+    fn faces_share_vertex_buffer() {
+        let mesh = TriangleMesh::new(
+            vec![
+                Point::new(0.0, 1.0, 0.0),
+                Point::new(-1.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+                Point::new(0.0, -1.0, 0.0),
+            ],
+            vec![[0, 1, 2], [1, 3, 2]],
+        );
+        assert_eq!(mesh.vertices().len(), 4);
+        assert_eq!(mesh.faces().len(), 2);
+        assert_eq!(mesh.normals[0], Vector::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn empty_mesh_samples_its_origin() {
+        let mesh = TriangleMesh::new(vec![Point::zero()], Vec::new());
+        let (point, normal) = mesh.sample_surface(&mut Rng::new(1));
+        assert_eq!(point, Point::zero());
+        assert_eq!(normal, Vector::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn empty_mesh_has_a_defined_normal() {
+        let mesh = TriangleMesh::new(Vec::new(), Vec::new());
+        assert_eq!(mesh.normal_at(&Point::zero()), Vector::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn quad_area_and_bounds() {
+        let quad = quad();
+        assert_eq!(quad.shape().surface_area(), 4.0);
+        let bounds = quad.shape().bounds();
+        assert_eq!(bounds.min(), Point::new(-1.0, 0.0, -1.0));
+        assert_eq!(bounds.max(), Point::new(1.0, 0.0, 1.0));
+    }
+}
@@ -13,14 +13,24 @@ impl<'a> Plane {
     }
 
     pub fn intersects(ray: &Ray, object: &'a Object) -> Intersections<'a> {
+        Plane::intersects_with_epsilon(ray, object, epsilon::EPSILON)
+    }
+
+    // Rays closer than parallel_epsilon to parallel with the plane miss it.
+    pub fn intersects_with_epsilon(ray: &Ray, object: &'a Object, parallel_epsilon: f64) -> Intersections<'a> {
         let mut intersections = Intersections::new();
-        if ray.direction().y().abs() < epsilon::EPSILON {
-            return intersections;
+        if let Some(t) = Plane::root(ray, parallel_epsilon) {
+            intersections.push(object, t);
         }
-        let t = -ray.origin().y() / ray.direction().y();
-        intersections.push(object, t);
         intersections
     }
+
+    pub fn root(ray: &Ray, parallel_epsilon: f64) -> Option<f64> {
+        if ray.direction().y().abs() < parallel_epsilon {
+            return None;
+        }
+        Some(-ray.origin().y() / ray.direction().y())
+    }
 }
 #[cfg(test)]
 mod tests {
@@ -72,4 +82,14 @@ mod tests {
     }
 
 
+
+    #[test]
+    fn flipped_plane_normal_points_the_other_way() {
+        let transform = crate::primitives::Matrix::id().rotate_x(std::f64::consts::FRAC_PI_2);
+        let plane = Object::new_plane().set_transform(transform);
+        let flipped = Object::new_flipped_plane().set_transform(transform);
+        let point = Point::new(1.0, 2.0, 0.0);
+        assert_eq!(plane.normal_at(&point), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(flipped.normal_at(&point), Vector::new(0.0, 0.0, -1.0));
+    }
 }
@@ -1,5 +1,6 @@
 use crate::primitives::Vector;
-use crate::rtc::intersection::Intersections;
+use crate::rtc::intersection::{Intersection, Intersections};
+use crate::rtc::uv::spherical_map;
 use crate::rtc::object::Object;
 use crate::rtc::ray::Ray;
 use crate::primitives::Point;
@@ -9,21 +10,37 @@ pub struct Sphere{}
 
 impl<'a> Sphere{
     pub fn intersects(ray: &Ray, object: &'a Object) -> Intersections<'a>{
-        let mut intersections = Intersections::new();
+        let (t1, t2) = match Sphere::roots(ray) {
+            Some(roots) => roots,
+            None => return Intersections::new(),
+        };
+        Intersections::new().with_intersections(
+            [t1, t2]
+                .into_iter()
+                .map(|t| {
+                    let (u, v) = Sphere::uv_at(&ray.position(t));
+                    Intersection::new(t, object).with_uv(u, v)
+                })
+                .collect(),
+        )
+    }
+
+    // Both roots in ascending order, or None when the ray misses.
+    pub fn roots(ray: &Ray) -> Option<(f64, f64)> {
         let sphere_to_ray = Point::zero() - ray.origin();
         let tc = sphere_to_ray.dot_product(&ray.direction().normalize());
         let l = sphere_to_ray.dot_product(&sphere_to_ray);
         let d2 = l - tc * tc;
         if d2 > 1.0 {
-            return Intersections::new(); 
+            return None;
         }
         let del_t = (1.0 - d2).sqrt() / ray.direction().magnitude();
         let tc = tc / ray.direction().magnitude();
-        let t1 = tc - del_t;
-        let t2 = tc + del_t;
-        intersections.push(object, t1);
-        intersections.push(object, t2);
-        intersections
+        Some((tc - del_t, tc + del_t))
+    }
+
+    pub fn uv_at(point: &Point) -> (f64, f64) {
+        spherical_map(point)
     }
     pub fn normal_at(point: &Point) -> Vector{
         *point - Point::zero()
@@ -63,32 +80,47 @@ mod tests{
     fn normal_on_translated_sphere(){
         let mut s = Object::new_sphere();
         let translate = Matrix::id().translate(0.0, 1.0, 0.0);
-        s = s.set_transform(&translate);
-        let n = s.normal_at(&Point::new(0.0, 1.70711, -0.70711));
-        assert_eq!(n, Vector::new(0.0, 0.70711, -0.70711));
+        s = s.set_transform(translate);
+        let frac_1_sqrt_2 = std::f64::consts::FRAC_1_SQRT_2;
+        let n = s.normal_at(&Point::new(0.0, 1.0 + frac_1_sqrt_2, -frac_1_sqrt_2));
+        assert_eq!(n, Vector::new(0.0, frac_1_sqrt_2, -frac_1_sqrt_2));
     }
 
     #[test]
     fn normal_on_transformed_sphere(){
         let mut s = Object::new_sphere();
         let transform = Matrix::id().rotate_z(std::f64::consts::PI/5.0).scale(1.0, 0.5, 1.0);
-        s = s.set_transform(&transform);
+        s = s.set_transform(transform);
         let n = s.normal_at(&Point::new(0.0, 2.0_f64.sqrt()/2.0, -2.0_f64.sqrt()/2.0));
         assert_eq!(n, Vector::new(0.0, 0.97014, -0.24254));
     }
+    #[test]
+    fn intersections_report_spherical_uv(){
+        let s = Object::new_sphere();
+        let r = Ray::new(Point::new(0.3, 0.4, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = Sphere::intersects(&r, &s);
+        for i in xs.iter() {
+            let (u, v) = spherical_map(&r.position(i.t()));
+            assert_eq!(i.u(), Some(u));
+            assert_eq!(i.v(), Some(v));
+        }
+        let plane = Object::new_plane();
+        let down = Ray::new(Point::new(0.0, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        assert_eq!(plane.intersect(&down)[0].u(), None);
+    }
+
     #[test]
     fn sphere_has_default_material(){
         let s = Object::new_sphere();
-        assert_eq!(s.material(), Material::new());
+        assert_eq!(*s.material(), Material::new());
     }
 
     #[test]
     fn sphere_may_be_assigned_material(){
         let mut s = Object::new_sphere();
-        let m = Material::new();
-        m.with_ambient(1.0);
+        let m = Material::new().with_ambient(1.0);
         s = s.set_material(&m);
-        assert_eq!(s.material(), m);
+        assert_eq!(*s.material(), m);
     }
 }
 
@@ -1,6 +1,96 @@
+use crate::float::ApproxEq;
 use crate::primitives::{Point, Vector, Matrix, Tuple};
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViewError {
+    NoDirection, // from and to coincide
+    ParallelUp,  // up runs along the view direction
+}
+
+impl std::fmt::Display for ViewError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ViewError::NoDirection => write!(f, "View starts and ends at the same point"),
+            ViewError::ParallelUp => write!(f, "View up vector is parallel to the view direction"),
+        }
+    }
+}
+
+impl std::error::Error for ViewError {}
+
+// Collects operations in the order they should be applied to an object, so
+// Transform::new().scale(..).translate(..) scales first and then translates.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Transform {
+    matrix: Matrix,
+}
+
+impl Transform {
+    pub fn new() -> Self {
+        Transform { matrix: Matrix::id() }
+    }
+
+    pub fn translate(mut self, x: f64, y: f64, z: f64) -> Self {
+        self.matrix = self.matrix.translate(x, y, z);
+        self
+    }
+
+    pub fn scale(mut self, x: f64, y: f64, z: f64) -> Self {
+        self.matrix = self.matrix.scale(x, y, z);
+        self
+    }
+
+    pub fn rotate_x(mut self, r: f64) -> Self {
+        self.matrix = self.matrix.rotate_x(r);
+        self
+    }
+
+    pub fn rotate_y(mut self, r: f64) -> Self {
+        self.matrix = self.matrix.rotate_y(r);
+        self
+    }
+
+    pub fn rotate_z(mut self, r: f64) -> Self {
+        self.matrix = self.matrix.rotate_z(r);
+        self
+    }
+
+    pub fn shear(mut self, xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Self {
+        self.matrix = self.matrix.shear(xy, xz, yx, yz, zx, zy);
+        self
+    }
+
+    pub fn matrix(&self) -> Matrix {
+        self.matrix
+    }
+}
+
+impl From<Transform> for Matrix {
+    fn from(transform: Transform) -> Matrix {
+        transform.matrix
+    }
+}
+
+fn check_view(from: Point, to: Point, up: Vector) -> Result<(), ViewError> {
+    let forward = to - from;
+    if forward.magnitude_squared().approx_eq(0.0) {
+        return Err(ViewError::NoDirection);
+    }
+    if forward.normalize().cross_product(up.normalize()).magnitude_squared().approx_eq(0.0) {
+        return Err(ViewError::ParallelUp);
+    }
+    Ok(())
+}
+
+// Fails when from and to coincide or up runs along the view direction, where
+// view_transform would produce NaNs.
+pub fn try_view_transform(from: Point, to: Point, up: Vector) -> Result<Matrix, ViewError> {
+    check_view(from, to, up)?;
+    Ok(view_transform(from, to, up))
+}
+
 pub fn view_transform(from: Point, to: Point, up: Vector) -> Matrix {
+    debug_assert_eq!(check_view(from, to, up), Ok(()), "degenerate view_transform");
     let forward = (to - from).normalize();
     let left = forward.cross_product(up.normalize());
     let true_up = left.cross_product(forward);
@@ -52,5 +142,35 @@ mod tests{
         assert_eq!(t, expected);
     }
 
+    #[test]
+    fn transform_builder_applies_left_to_right() {
+        let t = Transform::new()
+            .rotate_x(std::f64::consts::FRAC_PI_2)
+            .scale(5.0, 5.0, 5.0)
+            .translate(10.0, 5.0, 7.0);
+        let rotation = Matrix::id().rotate_x(std::f64::consts::FRAC_PI_2);
+        let scaling = Matrix::id().scale(5.0, 5.0, 5.0);
+        let translation = Matrix::id().translate(10.0, 5.0, 7.0);
+        assert_eq!(Matrix::from(t), translation * scaling * rotation);
+        let p = Point::new(1.0, 0.0, 1.0);
+        assert_eq!(t.matrix() * p, Point::new(15.0, 0.0, 7.0));
+    }
+
+    #[test]
+    fn degenerate_views_are_rejected() {
+        let p = Point::new(1.0, 2.0, 3.0);
+        let up = Vector::new(0.0, 1.0, 0.0);
+        assert_eq!(try_view_transform(p, p, up), Err(ViewError::NoDirection));
+        let above = Point::new(1.0, 7.0, 3.0);
+        assert_eq!(try_view_transform(p, above, up), Err(ViewError::ParallelUp));
+        assert_eq!(try_view_transform(p, Point::zero(), up), Ok(view_transform(p, Point::zero(), up)));
+    }
 
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "degenerate view_transform")]
+    fn view_transform_asserts_on_degenerate_view() {
+        let p = Point::new(1.0, 2.0, 3.0);
+        view_transform(p, p, Vector::new(0.0, 1.0, 0.0));
+    }
 }
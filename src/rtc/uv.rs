@@ -0,0 +1,157 @@
+use crate::primitives::{Point, Tuple};
+
+// Maps a point on the xz plane to (u, v) in [0, 1), repeating every tile_size units.
+pub fn planar_map(point: &Point, tile_size: f64) -> (f64, f64) {
+    let u = (point.x() / tile_size).rem_euclid(1.0);
+    let v = (point.z() / tile_size).rem_euclid(1.0);
+    (u, v)
+}
+
+// Maps a point to longitude and latitude around the origin; u grows counterclockwise seen from +y.
+pub fn spherical_map(point: &Point) -> (f64, f64) {
+    use std::f64::consts::PI;
+    let theta = point.x().atan2(point.z());
+    let radius = (point.x().powi(2) + point.y().powi(2) + point.z().powi(2)).sqrt();
+    let phi = (point.y() / radius).clamp(-1.0, 1.0).acos();
+    let u = 1.0 - (theta / (2.0 * PI) + 0.5);
+    let v = 1.0 - phi / PI;
+    (u, v)
+}
+
+// Wraps u around the y axis like spherical_map; v repeats every unit of height.
+pub fn cylindrical_map(point: &Point) -> (f64, f64) {
+    let theta = point.x().atan2(point.z());
+    let u = 1.0 - (theta / (2.0 * std::f64::consts::PI) + 0.5);
+    let v = point.y().rem_euclid(1.0);
+    (u, v)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CubeFace {
+    Left,
+    Right,
+    Front,
+    Back,
+    Up,
+    Down,
+}
+
+// Picks the face of the unit cube by the largest coordinate and maps the point into that face's
+// (u, v) in [0, 1]. Points on an edge or corner go to the first match in the order x, y, z.
+pub fn cube_map(point: &Point) -> (CubeFace, f64, f64) {
+    let (x, y, z) = (point.x(), point.y(), point.z());
+    let largest = x.abs().max(y.abs()).max(z.abs());
+    let wrap = |c: f64| c.rem_euclid(2.0) / 2.0;
+    if largest == x {
+        (CubeFace::Right, wrap(1.0 - z), wrap(y + 1.0))
+    } else if largest == -x {
+        (CubeFace::Left, wrap(z + 1.0), wrap(y + 1.0))
+    } else if largest == y {
+        (CubeFace::Up, wrap(x + 1.0), wrap(1.0 - z))
+    } else if largest == -y {
+        (CubeFace::Down, wrap(x + 1.0), wrap(z + 1.0))
+    } else if largest == z {
+        (CubeFace::Front, wrap(x + 1.0), wrap(y + 1.0))
+    } else {
+        (CubeFace::Back, wrap(1.0 - x), wrap(y + 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::float::ApproxEq;
+
+    #[test]
+    fn planar_map_unit_tiles() {
+        let cases = [
+            (Point::new(0.25, 0.0, 0.5), (0.25, 0.5)),
+            (Point::new(0.25, 0.0, -0.25), (0.25, 0.75)),
+            (Point::new(0.25, 0.5, -0.25), (0.25, 0.75)),
+            (Point::new(1.25, 0.0, 0.5), (0.25, 0.5)),
+            (Point::new(-0.25, 0.0, -1.75), (0.75, 0.25)),
+        ];
+        for (point, (u, v)) in cases {
+            let (pu, pv) = planar_map(&point, 1.0);
+            assert!(pu.approx_eq(u) && pv.approx_eq(v));
+        }
+    }
+
+    #[test]
+    fn planar_map_repeats_every_tile_size() {
+        let p = Point::new(0.5, 0.0, -0.5);
+        let (u, v) = planar_map(&p, 2.0);
+        assert!(u.approx_eq(0.25) && v.approx_eq(0.75));
+        for (dx, dz) in [(2.0, 0.0), (0.0, 2.0), (-4.0, 6.0)] {
+            let (su, sv) = planar_map(&Point::new(p.x() + dx, 0.0, p.z() + dz), 2.0);
+            assert!(su.approx_eq(u) && sv.approx_eq(v));
+        }
+        let (su, _) = planar_map(&Point::new(p.x() + 1.0, 0.0, p.z()), 2.0);
+        assert!(su.approx_eq(0.75));
+    }
+
+    #[test]
+    fn spherical_map_on_unit_sphere() {
+        let s = 2.0_f64.sqrt() / 2.0;
+        let cases = [
+            (Point::new(0.0, 0.0, -1.0), (0.0, 0.5)),
+            (Point::new(1.0, 0.0, 0.0), (0.25, 0.5)),
+            (Point::new(0.0, 0.0, 1.0), (0.5, 0.5)),
+            (Point::new(-1.0, 0.0, 0.0), (0.75, 0.5)),
+            (Point::new(0.0, 1.0, 0.0), (0.5, 1.0)),
+            (Point::new(0.0, -1.0, 0.0), (0.5, 0.0)),
+            (Point::new(s, s, 0.0), (0.25, 0.75)),
+        ];
+        for (point, (u, v)) in cases {
+            let (pu, pv) = spherical_map(&point);
+            assert!(pu.approx_eq(u) && pv.approx_eq(v), "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn cube_map_picks_face_by_largest_coordinate() {
+        let cases = [
+            (Point::new(-1.0, 0.5, -0.25), CubeFace::Left),
+            (Point::new(1.1, -0.75, 0.8), CubeFace::Right),
+            (Point::new(0.1, 0.6, 0.9), CubeFace::Front),
+            (Point::new(-0.7, 0.0, -2.0), CubeFace::Back),
+            (Point::new(0.5, 1.0, 0.9), CubeFace::Up),
+            (Point::new(-0.2, -1.3, 1.1), CubeFace::Down),
+        ];
+        for (point, face) in cases {
+            assert_eq!(cube_map(&point).0, face, "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn cube_map_face_uvs() {
+        let cases = [
+            (Point::new(-1.0, 0.5, -0.25), (CubeFace::Left, 0.375, 0.75)),
+            (Point::new(-1.0, -0.5, 0.25), (CubeFace::Left, 0.625, 0.25)),
+            (Point::new(1.0, 0.5, -0.25), (CubeFace::Right, 0.625, 0.75)),
+            (Point::new(-0.5, 0.5, 1.0), (CubeFace::Front, 0.25, 0.75)),
+            (Point::new(0.5, 0.5, -1.0), (CubeFace::Back, 0.25, 0.75)),
+            (Point::new(-0.5, 1.0, -0.5), (CubeFace::Up, 0.25, 0.75)),
+            (Point::new(-0.5, -1.0, 0.5), (CubeFace::Down, 0.25, 0.75)),
+        ];
+        for (point, (face, u, v)) in cases {
+            let (f, pu, pv) = cube_map(&point);
+            assert!(f == face && pu.approx_eq(u) && pv.approx_eq(v), "{:?}", point);
+        }
+    }
+
+    #[test]
+    fn cube_map_edges_are_deterministic() {
+        assert_eq!(cube_map(&Point::new(1.0, 1.0, 0.0)).0, CubeFace::Right);
+        assert_eq!(cube_map(&Point::new(0.0, -1.0, -1.0)).0, CubeFace::Down);
+        assert_eq!(cube_map(&Point::new(-1.0, 1.0, 1.0)).0, CubeFace::Left);
+    }
+
+    #[test]
+    fn cylindrical_map_wraps_and_repeats() {
+        let (u, v) = cylindrical_map(&Point::new(1.0, 1.25, 0.0));
+        assert!(u.approx_eq(0.25) && v.approx_eq(0.25));
+        let (u, v) = cylindrical_map(&Point::new(0.0, -0.25, 1.0));
+        assert!(u.approx_eq(0.5) && v.approx_eq(0.75));
+    }
+}